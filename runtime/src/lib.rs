@@ -61,9 +61,20 @@ pub type Hash = primitives::H256;
 /// Digest item type.
 pub type DigestItem = generic::DigestItem<Hash>;
 
+/// Balance of an account in the TCR token.
+pub type TokenBalance = u128;
+
+/// Timestamp in milliseconds, also used for the TCR stages.
+pub type Moment = u64;
+
+pub mod runtime_api;
+mod parameterizer;
 mod tcr;
 mod token;
 
+pub use tcr::{Challenge, Listing, ListingStatus, Poll, RegistryId, Vote};
+pub use runtime_api::TcrInstance;
+
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
 /// of data like extrinsics, allowing for them to continue syncing the network through upgrades
@@ -190,7 +201,7 @@ parameter_types! {
 
 impl timestamp::Trait for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
-	type Moment = u64;
+	type Moment = Moment;
 	type OnTimestampSet = Aura;
 	type MinimumPeriod = MinimumPeriod;
 }
@@ -236,13 +247,53 @@ impl sudo::Trait for Runtime {
 	type Proposal = Call;
 }
 
+parameter_types! {
+	pub const MaxResolutionsPerBlock: u32 = 10;
+	pub const MaxMigrationsPerBlock: u32 = 100;
+	/// Rejected listings can apply again after 2 mins (120000 millis).
+	pub const ReapplyCooldown: Moment = 120000;
+	/// Reserved from the creator of a registry, in the currency of each TCR.
+	pub const RegistryDeposit: TokenBalance = 1000;
+	pub const Tcr2RegistryDeposit: Balance = 1000;
+}
+
 impl tcr::Trait for Runtime {
 	type Event = Event;
+	/// Deposits and votes are staked in the TCR token.
+	type Currency = Token;
+	/// Stages are measured with timestamps, use `tcr::BlockNumberClock` to measure them in blocks.
+	type Clock = tcr::TimestampClock<Runtime>;
+	type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+	type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+	type ReapplyCooldown = ReapplyCooldown;
+	type RegistryDeposit = RegistryDeposit;
+	/// Stakes of the first release were locked in the token, they are reserved on the upgrade.
+	type LockedStakes = Token;
+}
+
+/// Stakes in the native currency are reserved, the TCR keeps the account of the stakes per listing.
+impl tcr::StakeCurrency<AccountId, Hash> for Balances {}
+
+/// A second, independent TCR. Its parameters are not governed by the parameterizer.
+/// Deposits and votes are staked in the native currency.
+impl tcr::Trait<tcr::Instance2> for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type Clock = tcr::TimestampClock<Runtime>;
+	type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+	type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+	type ReapplyCooldown = ReapplyCooldown;
+	type RegistryDeposit = Tcr2RegistryDeposit;
+	type LockedStakes = ();
+}
+
+impl parameterizer::Trait for Runtime {
+	type Event = Event;
 }
 
 impl token::Trait for Runtime {
 	type Event = Event;
-	type TokenBalance = u128;
+	type TokenBalance = TokenBalance;
 }
 
 construct_runtime!(
@@ -259,8 +310,11 @@ construct_runtime!(
 		Balances: balances::{default, Error},
 		TransactionPayment: transaction_payment::{Module, Storage},
 		Sudo: sudo,
-		Tcr: tcr::{Module, Call, Storage, Event<T>, Config<T>},
-		Token: token::{Module, Call, Storage, Event<T>, Config<T>},
+		// Built before the TCRs in genesis, initial listings reserve their deposits.
+		Token: token::{Module, Call, Storage, Event<T>, Config<T>, Error},
+		Tcr: tcr::{Module, Call, Storage, Event<T>, Config<T>, Error},
+		Tcr2: tcr::<Instance2>::{Module, Call, Storage, Event<T>, Config<T>, Error},
+		Parameterizer: parameterizer::{Module, Call, Storage, Event<T>},
 		RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
 	}
 );
@@ -291,6 +345,18 @@ pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Call, SignedExt
 /// Executive: handles dispatch to the various modules.
 pub type Executive = executive::Executive<Runtime, Block, system::ChainContext<Runtime>, Runtime, AllModules>;
 
+/// Runs a query on the TCR instance with the given `TcrInstance` index, as the module `$tcr`.
+/// Both instances share the balance and moment types, so the queries return the same types.
+macro_rules! with_tcr {
+	($instance:expr, $tcr:ident => $query:expr, $unknown:expr) => {
+		match $instance {
+			0 => { type $tcr = Tcr; $query }
+			1 => { type $tcr = Tcr2; $query }
+			_ => $unknown,
+		}
+	};
+}
+
 impl_runtime_apis! {
 	impl client_api::Core<Block> for Runtime {
 		fn version() -> RuntimeVersion {
@@ -368,4 +434,66 @@ impl_runtime_apis! {
 			Grandpa::grandpa_authorities()
 		}
 	}
+
+	impl runtime_api::TcrApi<Block, AccountId, TokenBalance, Hash, Moment> for Runtime {
+		fn listing(instance: TcrInstance, hash: Hash) -> Option<Listing<TokenBalance, AccountId, Moment>> {
+			with_tcr!(instance, T => T::get_listing(hash), None)
+		}
+
+		fn listings(
+			instance: TcrInstance,
+			registry_id: RegistryId,
+			page: u32,
+			page_size: u32,
+		) -> Vec<(Hash, Listing<TokenBalance, AccountId, Moment>)> {
+			with_tcr!(instance, T => T::listings_page(registry_id, page, page_size), Vec::new())
+		}
+
+		fn whitelisted(
+			instance: TcrInstance,
+			registry_id: RegistryId,
+			page: u32,
+			page_size: u32,
+		) -> Vec<(Hash, Listing<TokenBalance, AccountId, Moment>)> {
+			with_tcr!(instance, T => T::whitelisted_listings(registry_id, page, page_size), Vec::new())
+		}
+
+		fn challenge(instance: TcrInstance, id: u32) -> Option<Challenge<Hash, TokenBalance, AccountId, Moment>> {
+			with_tcr!(instance, T => T::get_challenge(id), None)
+		}
+
+		fn poll(instance: TcrInstance, id: u32) -> Option<Poll<Hash, TokenBalance>> {
+			with_tcr!(instance, T => T::get_poll(id), None)
+		}
+
+		fn vote(instance: TcrInstance, challenge_id: u32, account: AccountId) -> Option<Vote<Hash, TokenBalance>> {
+			with_tcr!(instance, T => T::get_vote(challenge_id, account), None)
+		}
+
+		fn claimable_reward(instance: TcrInstance, challenge_id: u32, account: AccountId) -> Option<TokenBalance> {
+			with_tcr!(instance, T => T::claimable_reward(challenge_id, account), None)
+		}
+
+		fn pending_rewards(instance: TcrInstance, account: AccountId, page: u32, page_size: u32) -> Vec<(u32, TokenBalance)> {
+			with_tcr!(instance, T => T::pending_rewards(account, page, page_size), Vec::new())
+		}
+	}
+
+	impl runtime_api::TokenApi<Block, AccountId, TokenBalance> for Runtime {
+		fn balance_of(account: AccountId) -> TokenBalance {
+			Token::balance_of(account)
+		}
+
+		fn name() -> Vec<u8> {
+			Token::name()
+		}
+
+		fn symbol() -> Vec<u8> {
+			Token::symbol()
+		}
+
+		fn decimals() -> u8 {
+			Token::decimals()
+		}
+	}
 }
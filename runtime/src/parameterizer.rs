@@ -0,0 +1,378 @@
+/// Runtime module for governance of the TCR parameters.
+/// Token holders propose parameter changes to a registry, backed by a deposit.
+/// Proposals can be challenged, and a challenge is decided by token-weighted voting,
+/// using the same challenges, polls and votes as challenges on listings in the TCR module.
+/// Votes on a proposal challenge are committed, revealed and claimed through the TCR module calls.
+
+use crate::tcr::{self, BalanceOf, MomentOf, RegistryId, StageClock};
+use codec::{Decode, Encode};
+use rstd::prelude::*;
+use sr_primitives::{Permill, traits::{CheckedAdd, Hash}};
+use support::{
+  decl_event, decl_module, decl_storage, dispatch::Result, print, ensure, StorageMap,
+};
+use system::ensure_signed;
+
+// The module trait
+pub trait Trait: tcr::Trait {
+  type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+// Generic type parameters - Balance, Moment
+// A change to one of the TCR parameters.
+pub enum ParamChange<U, W> {
+  MinDeposit(U),
+  ApplyStageLen(W),
+  CommitStageLen(W),
+  RevealStageLen(W),
+  DispensationPct(Permill),
+}
+
+impl<U: Default, W> Default for ParamChange<U, W> {
+  fn default() -> Self {
+    ParamChange::MinDeposit(Default::default())
+  }
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+// Generic type parameters - Balance, AccountId, Moment
+pub struct Proposal<U, V, W> {
+  pub registry_id: RegistryId,
+  pub change: ParamChange<U, W>,
+  pub deposit: U,
+  pub owner: V,
+  pub application_expiry: W,
+  pub challenge_id: u32,
+}
+
+// Storage
+decl_storage! {
+  trait Store for Module<T: Trait> as Parameterizer {
+    // Parameter change proposals, by the hash of the registry id and the change.
+    Proposals get(proposals): map T::Hash => Proposal<BalanceOf<T>, T::AccountId, MomentOf<T>>;
+  }
+}
+
+// Events
+decl_event!(
+  pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
+  Balance = BalanceOf<T>,
+  Hash = <T as system::Trait>::Hash {
+    // When a parameter change is proposed.
+    Proposed(AccountId, Hash, Balance),
+    // When a parameter change proposal is challenged.
+    Challenged(AccountId, Hash, u32, Balance),
+    // When a parameter change is accepted and applied.
+    Accepted(Hash),
+    // When a parameter change is rejected.
+    Rejected(Hash),
+  }
+);
+
+// Module impl
+decl_module! {
+  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+    // Initialize events for this module.
+    fn deposit_event() = default;
+
+    // Propose a change to a TCR parameter of a registry.
+    // Only the genesis registry is governed by token holders, admins configure the registries they created.
+    // Takes deposit as stake backing the proposal.
+    // Same minimum deposit and apply stage length as for listings of the registry.
+    fn propose(origin,
+      registry_id: RegistryId,
+      change: ParamChange<BalanceOf<T>, MomentOf<T>>,
+      #[compact] deposit: BalanceOf<T>) -> Result {
+
+      let sender = ensure_signed(origin)?;
+      <tcr::Module<T>>::ensure_can_stake(&sender, deposit)?;
+      ensure!(registry_id == 0, "Only the parameters of the genesis registry can be proposed.");
+
+      let registry = <tcr::Module<T>>::registry(registry_id)?;
+      ensure!(deposit >= registry.min_deposit, "deposit should be more than min_deposit");
+
+      let now = T::Clock::now();
+      let app_exp = now.checked_add(&registry.apply_stage_len).ok_or("Overflow when setting application expiry.")?;
+
+      let hashed = <T as system::Trait>::Hashing::hash_of(&(registry_id, &change));
+      ensure!(!<Proposals<T>>::exists(hashed), "Proposal already exists");
+
+      let proposal = Proposal {
+        registry_id,
+        change,
+        deposit,
+        owner: sender.clone(),
+        application_expiry: app_exp,
+        challenge_id: 0,
+      };
+
+      // Reserve the deposit for the proposal.
+      <tcr::Module<T>>::reserve_stake(&sender, hashed, deposit)?;
+
+      <Proposals<T>>::insert(hashed, proposal);
+
+      Self::deposit_event(RawEvent::Proposed(sender, hashed, deposit));
+      print("Parameter change proposed!");
+
+      Ok(())
+    }
+
+    // Challenge a parameter change proposal during its apply stage.
+    // Creates a challenge in the TCR module, voters use the TCR module to vote on it.
+    fn challenge(origin, proposal_hash: T::Hash, #[compact] deposit: BalanceOf<T>) -> Result {
+      let sender = ensure_signed(origin)?;
+      <tcr::Module<T>>::ensure_can_stake(&sender, deposit)?;
+
+      ensure!(<Proposals<T>>::exists(proposal_hash), "Proposal not found.");
+      let proposal = Self::proposals(proposal_hash);
+
+      ensure!(proposal.challenge_id == 0, "Proposal is already challenged.");
+      ensure!(proposal.owner != sender, "You cannot challenge your own proposal.");
+      ensure!(deposit >= proposal.deposit, "Not enough deposit to challenge.");
+
+      let now = T::Clock::now();
+      ensure!(proposal.application_expiry > now, "Apply stage length has passed.");
+
+      let challenge_id = <tcr::Module<T>>::new_challenge(proposal.registry_id, sender.clone(), proposal_hash, deposit)?;
+
+      <Proposals<T>>::mutate(proposal_hash, |proposal| {
+        proposal.challenge_id = challenge_id;
+      });
+
+      Self::deposit_event(RawEvent::Challenged(sender, proposal_hash, challenge_id, deposit));
+      print("Proposal challenged!");
+
+      Ok(())
+    }
+
+    // Process a proposal once its apply stage, or the reveal stage of its challenge, has passed.
+    // Unchallenged proposals and proposals that win their challenge are applied.
+    // Proposal deposit is returned to the owner if the proposal is applied, and forfeited otherwise.
+    fn process(_origin, proposal_hash: T::Hash) -> Result {
+      ensure!(<Proposals<T>>::exists(proposal_hash), "Proposal not found.");
+      let proposal = Self::proposals(proposal_hash);
+
+      let accepted = if proposal.challenge_id == 0 {
+        let now = T::Clock::now();
+        ensure!(proposal.application_expiry < now, "Apply stage length has not passed.");
+        true
+      } else {
+        <tcr::Module<T>>::settle_challenge(proposal.challenge_id, proposal.owner.clone(), proposal.deposit)?
+      };
+
+      if accepted {
+        <tcr::Module<T>>::release_stake(&proposal.owner, proposal_hash, proposal.deposit)?;
+        Self::apply_change(proposal.registry_id, proposal.change);
+        Self::deposit_event(RawEvent::Accepted(proposal_hash));
+      } else {
+        Self::deposit_event(RawEvent::Rejected(proposal_hash));
+      }
+
+      <Proposals<T>>::remove(proposal_hash);
+      Ok(())
+    }
+  }
+}
+
+// Utility and private functions.
+impl<T: Trait> Module<T> {
+  // Writes an accepted change to the TCR parameters of a registry.
+  fn apply_change(registry_id: RegistryId, change: ParamChange<BalanceOf<T>, MomentOf<T>>) {
+    <tcr::Registries<T>>::mutate(registry_id, |registry| match change {
+      ParamChange::MinDeposit(value) => registry.min_deposit = value,
+      ParamChange::ApplyStageLen(value) => registry.apply_stage_len = value,
+      ParamChange::CommitStageLen(value) => registry.commit_stage_len = value,
+      ParamChange::RevealStageLen(value) => registry.reveal_stage_len = value,
+      ParamChange::DispensationPct(value) => registry.dispensation_pct = value,
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token;
+
+  use primitives::{Blake2Hasher, H256};
+  use runtime_io::with_externalities;
+  use sr_primitives::{
+    Perbill,
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+  };
+  use support::{assert_noop, assert_ok, impl_outer_origin, parameter_types};
+
+  impl_outer_origin! {
+    pub enum Origin for Test {}
+  }
+
+  // For testing the module, we construct most of a mock runtime. This means
+  // first constructing a configuration type (`Test`) which `impl`s each of the
+  // configuration traits of modules we want to use.
+  #[derive(Clone, Eq, PartialEq)]
+  pub struct Test;
+  parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: u32 = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::one();
+  }
+  impl system::Trait for Test {
+    type Origin = Origin;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Call = ();
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+  }
+  impl token::Trait for Test {
+    type Event = ();
+    type TokenBalance = u64;
+  }
+  parameter_types! {
+    pub const MinimumPeriod: u64 = 1;
+  }
+  impl timestamp::Trait for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+  }
+  parameter_types! {
+    pub const MaxResolutionsPerBlock: u32 = 2;
+    pub const MaxMigrationsPerBlock: u32 = 2;
+    pub const ReapplyCooldown: u64 = 5;
+    pub const RegistryDeposit: u64 = 50;
+  }
+  impl tcr::Trait for Test {
+    type Event = ();
+    type Currency = token::Module<Test>;
+    type Clock = tcr::TimestampClock<Test>;
+    type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+    type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+    type ReapplyCooldown = ReapplyCooldown;
+    type RegistryDeposit = RegistryDeposit;
+    type LockedStakes = token::Module<Test>;
+  }
+  impl Trait for Test {
+    type Event = ();
+  }
+  type Parameterizer = Module<Test>;
+  type Tcr = tcr::Module<Test>;
+  type Token = token::Module<Test>;
+
+  // Builds the genesis config store and sets mock values.
+  fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    token::GenesisConfig::<Test> {
+      name: b"Test Token".to_vec(),
+      symbol: b"TST".to_vec(),
+      decimals: 0,
+      total_supply: 1000,
+      owner: 1,
+      max_supply: None,
+      balances: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    tcr::GenesisConfig::<Test> {
+      owner: 1,
+      min_deposit: 100,
+      apply_stage_len: 10,
+      commit_stage_len: 10,
+      reveal_stage_len: 10,
+      dispensation_pct: Permill::from_percent(50),
+      poll_nonce: 1,
+      initial_listings: vec![],
+      initial_admins: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    t.into()
+  }
+
+  // Initializes the TCR and gives tokens to accounts 2 and 3.
+  fn setup() {
+    assert_ok!(Token::init(Origin::signed(1)));
+    assert_ok!(Tcr::init(Origin::signed(1)));
+    assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+    assert_ok!(Token::transfer(Origin::signed(1), 3, 300));
+  }
+
+  #[test]
+  fn should_apply_unchallenged_proposal() {
+    with_externalities(&mut new_test_ext(), || {
+      setup();
+      let change = ParamChange::MinDeposit(200);
+      let hashed = <Test as system::Trait>::Hashing::hash_of(&(0u32, &change));
+      assert_ok!(Parameterizer::propose(Origin::signed(2), 0, change, 100));
+      assert_noop!(Parameterizer::process(Origin::signed(2), hashed), "Apply stage length has not passed.");
+
+      <timestamp::Module<Test>>::set_timestamp(11);
+      assert_ok!(Parameterizer::process(Origin::signed(2), hashed));
+      assert_eq!(Tcr::registries(0).min_deposit, 200);
+      assert_eq!(Token::balance_of(2), 300);
+      assert!(!<Proposals<Test>>::exists(hashed));
+    });
+  }
+
+  #[test]
+  fn should_reject_proposal_on_successful_challenge() {
+    with_externalities(&mut new_test_ext(), || {
+      setup();
+      let change = ParamChange::DispensationPct(Permill::from_percent(90));
+      let hashed = <Test as system::Trait>::Hashing::hash_of(&(0u32, &change));
+      assert_ok!(Parameterizer::propose(Origin::signed(2), 0, change, 100));
+      assert_ok!(Parameterizer::challenge(Origin::signed(3), hashed, 100));
+
+      // Challenge id 1, votes go through the TCR module.
+      assert_ok!(Tcr::commit_vote(Origin::signed(1), 0, 1, Tcr::commit_hash(1, &1, false, H256::from_low_u64_be(7)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(1), 0, 1, false, H256::from_low_u64_be(7)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Parameterizer::process(Origin::signed(3), hashed));
+
+      assert_eq!(Tcr::registries(0).dispensation_pct, Permill::from_percent(50));
+      // Challenger gets the deposit back plus half of the proposal's stake.
+      assert_eq!(Token::balance_of(3), 300 + 50);
+      assert_eq!(Token::balance_of(2), 200);
+    });
+  }
+
+  #[test]
+  fn should_fail_propose_for_created_registry() {
+    with_externalities(&mut new_test_ext(), || {
+      setup();
+      assert_ok!(Tcr::create_registry(Origin::signed(2), 10, 5, 5, 5, Permill::zero()));
+      assert_noop!(
+        Parameterizer::propose(Origin::signed(3), 1, ParamChange::MinDeposit(20), 100),
+        "Only the parameters of the genesis registry can be proposed."
+      );
+    });
+  }
+
+  #[test]
+  fn should_fail_challenge_own_proposal() {
+    with_externalities(&mut new_test_ext(), || {
+      setup();
+      let change = ParamChange::ApplyStageLen(20);
+      let hashed = <Test as system::Trait>::Hashing::hash_of(&(0u32, &change));
+      assert_ok!(Parameterizer::propose(Origin::signed(2), 0, change, 100));
+      assert_noop!(
+        Parameterizer::challenge(Origin::signed(2), hashed, 100),
+        "You cannot challenge your own proposal."
+      );
+    });
+  }
+}
@@ -0,0 +1,54 @@
+//! Runtime APIs of the TCR runtime.
+//! Front-ends and RPCs use these to read decoded module state instead of raw storage.
+
+use rstd::prelude::*;
+use codec::Codec;
+use crate::tcr::{Challenge, Listing, Poll, RegistryId, Vote};
+
+/// Index of a TCR instance of the runtime, `0` for `Tcr` and `1` for `Tcr2`.
+pub type TcrInstance = u32;
+
+client::decl_runtime_apis! {
+	/// Queries on the state of the TCR instances.
+	/// Queries on an unknown instance return `None` or nothing.
+	pub trait TcrApi<AccountId, Balance, Hash, Moment> where
+		AccountId: Codec,
+		Balance: Codec,
+		Hash: Codec,
+		Moment: Codec,
+	{
+		/// Get a listing by its hash.
+		fn listing(instance: TcrInstance, hash: Hash) -> Option<Listing<Balance, AccountId, Moment>>;
+		/// Get a page of the listings of a registry, with their hashes.
+		/// Pages are at most 100 listings long.
+		fn listings(instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Vec<(Hash, Listing<Balance, AccountId, Moment>)>;
+		/// Get the whitelisted listings on a page of the listings of a registry, with their hashes.
+		/// Pages are at most 100 listings long.
+		fn whitelisted(instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Vec<(Hash, Listing<Balance, AccountId, Moment>)>;
+		/// Get a challenge by its id.
+		fn challenge(instance: TcrInstance, id: u32) -> Option<Challenge<Hash, Balance, AccountId, Moment>>;
+		/// Get the poll of a challenge.
+		fn poll(instance: TcrInstance, id: u32) -> Option<Poll<Hash, Balance>>;
+		/// Get the vote of an account on a challenge.
+		fn vote(instance: TcrInstance, challenge_id: u32, account: AccountId) -> Option<Vote<Hash, Balance>>;
+		/// Tokens an account can claim for its vote on a challenge.
+		fn claimable_reward(instance: TcrInstance, challenge_id: u32, account: AccountId) -> Option<Balance>;
+		/// Rewards an account can claim on a page of the challenges, by challenge id.
+		/// Pages are at most 100 challenges long.
+		fn pending_rewards(instance: TcrInstance, account: AccountId, page: u32, page_size: u32) -> Vec<(u32, Balance)>;
+	}
+
+	/// Queries on the state of the TCR token.
+	pub trait TokenApi<AccountId, Balance> where
+		AccountId: Codec,
+		Balance: Codec,
+	{
+		/// Get the token balance of an account.
+		fn balance_of(account: AccountId) -> Balance;
+		/// Get the name of the token.
+		fn name() -> Vec<u8>;
+		/// Get the ticker symbol of the token.
+		fn symbol() -> Vec<u8>;
+		/// Get the number of decimals used to display balances.
+		fn decimals() -> u8;
+	}
@@ -1,24 +1,197 @@
-use crate::token;
 use codec::{Decode, Encode};
 use rstd::prelude::*;
-use sr_primitives::traits::{CheckedAdd, CheckedDiv, CheckedMul, Hash};
+use primitives::U256;
+use rstd::convert::{TryFrom, TryInto};
+use sr_primitives::{
+  ModuleId, Permill,
+  traits::{
+    AccountIdConversion, CheckedAdd, CheckedSub, Hash, MaybeSerializeDeserialize, Member, One, SaturatedConversion,
+    Saturating, SimpleArithmetic, Zero,
+  },
+};
 use support::{
-  decl_event, decl_module, decl_storage, dispatch::Result, print, ensure,
+  decl_error, decl_event, decl_module, decl_storage, dispatch, print, ensure, storage::{generator, unhashed},
+  traits::{Currency, ExistenceRequirement, Get, ReservableCurrency, WithdrawReasons}, Parameter,
 };
-use {system::ensure_signed, timestamp};
+use {system::{ensure_root, ensure_signed}, timestamp};
+use rstd::marker::PhantomData;
+#[cfg(feature = "std")]
+use serde::Serialize;
 
 // Read TCR concepts here:
 // https://www.gautamdhameja.com/token-curated-registries-explain-eli5-a5d4cce0ddbe/
 
 // The module trait
-pub trait Trait: timestamp::Trait + token::Trait {
-  type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+// The module is instanceable, a runtime can include several TCRs, each with its own storage.
+pub trait Trait<I: Instance = DefaultInstance>: system::Trait {
+  type Event: From<Event<Self, I>> + Into<<Self as system::Trait>::Event>;
+  // Currency of the deposits and votes, e.g. the `token` or the `balances` module.
+  // Stakes are locked per listing while they are at stake and moved to the TCR account when they are forfeited.
+  type Currency: StakeCurrency<Self::AccountId, Self::Hash>;
+  // Clock used for the stage lengths and deadlines, timestamps or block numbers.
+  type Clock: StageClock;
+  // Maximum number of listings resolved automatically in a block.
+  type MaxResolutionsPerBlock: Get<u32>;
+  // Maximum number of challenges and listings of version 0 migrated in a block, see `migrate_from_v0`.
+  type MaxMigrationsPerBlock: Get<u32>;
+  // Time a rejected listing has to wait before the same data can apply again, zero for none.
+  type ReapplyCooldown: Get<<Self::Clock as StageClock>::Moment>;
+  // Deposit reserved from the creator of a registry, for as long as the registry exists.
+  type RegistryDeposit: Get<BalanceOf<Self, I>>;
+  // Stakes locked in the currency by version 0 of the module, reserved when the storage is migrated.
+  // `()` if the currency never held any.
+  type LockedStakes: LockedStakes<Self::AccountId, Self::Hash, BalanceOf<Self, I>>;
+}
+
+// Source of the current moment for the TCR stages.
+// Stage lengths, application expiries and challenge deadlines are all in this unit.
+pub trait StageClock {
+  type Moment: Parameter + Member + SimpleArithmetic + MaybeSerializeDeserialize + Default + Copy;
+  fn now() -> Self::Moment;
+  // Number of blocks after the current one until a moment is expected to have passed, at least one.
+  // Automatic resolutions are scheduled in that block.
+  fn blocks_until(moment: Self::Moment) -> u64;
+}
+
+// Stages measured in milliseconds, using the `timestamp` module.
+pub struct TimestampClock<T>(PhantomData<T>);
+
+impl<T: timestamp::Trait> StageClock for TimestampClock<T> where T::Moment: MaybeSerializeDeserialize {
+  type Moment = T::Moment;
+  fn now() -> Self::Moment {
+    <timestamp::Module<T>>::get()
+  }
+  // Blocks are expected every two minimum periods, the slot duration with Aura.
+  // Slower blocks delay the automatic resolutions, faster ones make them move to a later block.
+  fn blocks_until(moment: Self::Moment) -> u64 {
+    let slot = T::MinimumPeriod::get().saturating_add(T::MinimumPeriod::get());
+    if slot.is_zero() {
+      return 1;
+    }
+    (moment.saturating_sub(Self::now()) / slot).saturated_into::<u64>().saturating_add(1)
+  }
+}
+
+// Stages measured in blocks.
+// Not affected by block authors skewing timestamps.
+pub struct BlockNumberClock<T>(PhantomData<T>);
+
+impl<T: system::Trait> StageClock for BlockNumberClock<T> {
+  type Moment = T::BlockNumber;
+  fn now() -> Self::Moment {
+    <system::Module<T>>::block_number()
+  }
+  fn blocks_until(moment: Self::Moment) -> u64 {
+    moment.saturating_sub(Self::now()).saturated_into::<u64>().saturating_add(1)
+  }
+}
+
+// Currency the stakes are locked in, per account and listing or proposal hash.
+// The default methods reserve the stakes, for currencies like `balances` that keep no account of the hashes.
+// The token module keeps its own account per hash, and never unlocks more than was locked for a hash.
+pub trait StakeCurrency<AccountId, Hash>: ReservableCurrency<AccountId> {
+  // Whether an account can lock a stake, `lock` only fails if this is false.
+  fn can_lock(who: &AccountId, value: Self::Balance) -> bool {
+    Self::can_reserve(who, value)
+  }
+  // Moves a stake from the free balance of an account to its stakes for a hash.
+  fn lock(who: &AccountId, _subject_hash: &Hash, value: Self::Balance) -> dispatch::Result {
+    Self::reserve(who, value)
+  }
+  // Returns a stake for a hash to the free balance of the account.
+  fn unlock(who: &AccountId, _subject_hash: &Hash, value: Self::Balance) -> dispatch::Result {
+    ensure!(Self::reserved_balance(who) >= value, "Not enough reserved balance.");
+    Self::unreserve(who, value);
+    Ok(())
+  }
+  // Moves a stake for a hash to the free balance of another account.
+  fn repatriate_locked(
+    who: &AccountId,
+    _subject_hash: &Hash,
+    beneficiary: &AccountId,
+    value: Self::Balance,
+  ) -> dispatch::Result {
+    ensure!(Self::reserved_balance(who) >= value, "Not enough reserved balance.");
+    Self::repatriate_reserved(who, beneficiary, value).map(drop)
+  }
+}
+
+// Stakes locked in a currency by version 0 of the module, before stakes were reserved.
+// They were held per listing hash rather than per account.
+pub trait LockedStakes<AccountId, Hash, Balance> {
+  // Stakes still locked for a listing hash.
+  fn locked(listing_hash: &Hash) -> Balance;
+  // Moves up to `value` of the stakes locked for a listing hash to the free balance of an account.
+  // Returns the amount moved.
+  fn unlock(listing_hash: &Hash, who: &AccountId, value: Balance) -> Balance;
+}
+
+impl<AccountId, Hash, Balance: Zero> LockedStakes<AccountId, Hash, Balance> for () {
+  fn locked(_: &Hash) -> Balance {
+    Zero::zero()
+  }
+  fn unlock(_: &Hash, _: &AccountId, _: Balance) -> Balance {
+    Zero::zero()
+  }
+}
+
+pub type MomentOf<T, I = DefaultInstance> = <<T as Trait<I>>::Clock as StageClock>::Moment;
+
+pub type BalanceOf<T, I = DefaultInstance> =
+  <<T as Trait<I>>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+// Version of the storage layout, see `StorageVersion`.
+const CURRENT_STORAGE_VERSION: u32 = 1;
+
+// Identifies the account holding forfeited stakes until they are paid out as dispensations and rewards.
+const MODULE_ID: ModuleId = ModuleId(*b"py/tcrrg");
+
+// Challenges scanned by a single `pending_rewards` query at most.
+const MAX_REWARDS_PAGE_SIZE: u32 = 100;
+
+// Listing ids scanned by a single `listings_page` or `whitelisted_listings` query at most.
+const MAX_LISTINGS_PAGE_SIZE: u32 = 100;
+
+// Registries are identified by a sequential id.
+// The registry set up in the genesis config has id 0.
+pub type RegistryId = u32;
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+// Generic type parameters - AccountId, Balance, Moment
+// A curated list with its own owner and TCR parameters.
+pub struct Registry<V, U, W> {
+  pub owner: V,
+  // TCR parameter - minimum deposit.
+  pub min_deposit: U,
+  // TCR parameter - apply stage length - deadline for challenging before a listing gets accepted.
+  pub apply_stage_len: W,
+  // TCR parameter - commit stage length - deadline for voting before a challenge gets resolved.
+  pub commit_stage_len: W,
+  // TCR parameter - reveal stage length - deadline for revealing committed votes.
+  pub reveal_stage_len: W,
+  // TCR parameter - dispensation percentage - share of the losing stake given to the winning party of a challenge.
+  // The remaining part goes to the voters on the winning side.
+  pub dispensation_pct: Permill,
 }
 
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
-// Generic type parameters - Balance, AccountId, timestamp::Moment
+// Generic type parameters - Balance, AccountId, Moment
 pub struct Listing<U, V, W> {
+  pub id: u32,
+  pub registry_id: RegistryId,
+  pub data: Vec<u8>,
+  pub deposit: U,
+  pub owner: V,
+  pub application_expiry: W,
+  pub status: ListingStatus,
+}
+
+// Layouts of version 0 of the storage, the first release of the module.
+// Only used to migrate the entries stored in these layouts, see `migrate_from_v0`.
+#[derive(Encode, Decode)]
+struct ListingV0<U, V, W> {
   id: u32,
   data: Vec<u8>,
   deposit: U,
@@ -28,10 +201,8 @@ pub struct Listing<U, V, W> {
   challenge_id: u32,
 }
 
-#[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Encode, Decode, Default, Clone, PartialEq)]
-// Generic type parameters - Hash, Balance, AccountId, timestamp::Moment
-pub struct Challenge<T, U, V, W> {
+#[derive(Encode, Decode)]
+struct ChallengeV0<T, U, V, W> {
   listing_hash: T,
   deposit: U,
   owner: V,
@@ -41,67 +212,350 @@ pub struct Challenge<T, U, V, W> {
   total_tokens: U,
 }
 
-#[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Encode, Decode, Default, Clone, PartialEq)]
-// Generic type parameters - Balance
-pub struct Vote<U> {
+// Votes were counted as soon as they were cast, they are migrated as revealed votes.
+#[derive(Encode, Decode)]
+struct VoteV0<U> {
   value: bool,
   deposit: U,
   claimed: bool,
 }
 
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+// Status of a listing.
+// Valid transitions are
+//    Applied -> Challenged, Whitelisted
+//    Whitelisted -> Challenged, Exited
+//    Challenged -> Whitelisted, Rejected if it was an application, Removed if it was whitelisted
+// Rejected, Exited and Removed are final, the listing is removed from the registry.
+pub enum ListingStatus {
+  // In the apply stage, or waiting to be resolved once it has passed.
+  Applied,
+  // Under challenge, with the id of the challenge and whether the listing was whitelisted.
+  // A whitelisted listing stays in the registry until the challenge is resolved.
+  Challenged { challenge_id: u32, whitelisted: bool },
+  // Accepted in the registry.
+  Whitelisted,
+  // Application rejected by a challenge, with the id of the challenge.
+  Rejected(u32),
+  // Removed from the registry by its owner.
+  Exited,
+  // Whitelisted listing removed by a challenge, with the id of the challenge.
+  Removed(u32),
+}
+
+impl Default for ListingStatus {
+  fn default() -> Self {
+    ListingStatus::Applied
+  }
+}
+
+impl ListingStatus {
+  // Whether the listing is in the registry, including whitelisted listings under challenge.
+  pub fn is_whitelisted(&self) -> bool {
+    match self {
+      ListingStatus::Whitelisted | ListingStatus::Challenged { whitelisted: true, .. } => true,
+      _ => false,
+    }
+  }
+
+  // Id of the ongoing challenge, if any.
+  pub fn challenge_id(&self) -> Option<u32> {
+    match self {
+      ListingStatus::Challenged { challenge_id, .. } => Some(*challenge_id),
+      _ => None,
+    }
+  }
+
+  // Final statuses, the listing is removed from the registry.
+  pub fn is_final(&self) -> bool {
+    match self {
+      ListingStatus::Rejected(_) | ListingStatus::Exited | ListingStatus::Removed(_) => true,
+      _ => false,
+    }
+  }
+
+  // Whether a listing can move from this status to the next one.
+  pub fn can_become(&self, next: &ListingStatus) -> bool {
+    use ListingStatus::*;
+    match (self, next) {
+      (Applied, Challenged { whitelisted: false, .. }) | (Applied, Whitelisted) => true,
+      (Whitelisted, Challenged { whitelisted: true, .. }) | (Whitelisted, Exited) => true,
+      (Challenged { .. }, Whitelisted) => true,
+      (Challenged { challenge_id, whitelisted: false }, Rejected(id)) => challenge_id == id,
+      (Challenged { challenge_id, whitelisted: true }, Removed(id)) => challenge_id == id,
+      _ => false,
+    }
+  }
+}
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+// Generic type parameters - Hash, Balance, AccountId, Moment
+pub struct Challenge<T, U, V, W> {
+  pub registry_id: RegistryId,
+  pub listing_hash: T,
+  pub deposit: U,
+  pub owner: V,
+  pub voting_ends: W,
+  pub reveal_ends: W,
+  pub resolved: bool,
+  pub reward_pool: U,
+  pub total_tokens: U,
+  // Winning vote deposits that have claimed and the rewards paid for them.
+  // Used to find the rounding dust left in the reward pool once every winner has claimed.
+  pub claimed_tokens: U,
+  pub paid_rewards: U,
+}
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+// Generic type parameters - Hash, Balance
+// The value stays hidden behind `secret_hash` until the vote is revealed.
+pub struct Vote<T, U> {
+  pub secret_hash: T,
+  pub value: bool,
+  pub deposit: U,
+  pub revealed: bool,
+  pub claimed: bool,
+}
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize))]
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
 // Generic type parameters - Balance
 pub struct Poll<T, U> {
-  listing_hash: T,
-  votes_for: U,
-  votes_against: U,
-  passed: bool,
+  pub listing_hash: T,
+  pub votes_for: U,
+  pub votes_against: U,
+  pub passed: bool,
 }
 
 // Storage
 decl_storage! {
-  trait Store for Module<T: Trait> as Tcr {
-    // Stores the owner in the genesis config.
-    Owner get(owner) config(): T::AccountId;
-    // Stores a list of admins who can set config.
-    Admins get(admins): map T::AccountId => bool;
-    // TCR parameter - minimum deposit.
-    MinDeposit get(min_deposit) config(): Option<T::TokenBalance>;
-    // TCR parameter - apply stage length - deadline for challenging before a listing gets accepted.
-    ApplyStageLen get(apply_stage_len) config(): Option<T::Moment>;
-    // TCR parameter - commit stage length - deadline for voting before a challenge gets resolved.
-    CommitStageLen get(commit_stage_len) config(): Option<T::Moment>;
-    // The TCR - list of proposals.
-    Listings get(listings): map T::Hash => Listing<T::TokenBalance, T::AccountId, T::Moment>;
-    // To make querying of listings easier, maintaining a list of indexes and corresponding listing hashes.
-    ListingCount get(listing_count): u32;
-    ListingIndexHash get(index_hash): map u32 => T::Hash;
+  trait Store for Module<T: Trait<I>, I: Instance = DefaultInstance> as Tcr {
+    // Registries with their owners and parameters.
+    // Public to let the parameterizer module change the parameters.
+    pub Registries get(registries): map RegistryId => Registry<T::AccountId, BalanceOf<T, I>, MomentOf<T, I>>;
+    // Number of registries, also the id of the next registry.
+    RegistryCount get(registry_count): RegistryId;
+    // Stores a list of admins for each registry.
+    Admins get(admins): map (RegistryId, T::AccountId) => bool;
+    // The TCR - list of proposals of all registries.
+    // Listing hashes include the registry id, so the same data can be listed in several registries.
+    Listings get(listings): map T::Hash => Listing<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>;
+    // To make querying of listings easier, maintaining a list of indexes and corresponding listing hashes per registry.
+    ListingCount get(listing_count): map RegistryId => u32;
+    ListingIndexHash get(index_hash): map (RegistryId, u32) => T::Hash;
     // global nonce for poll count.
     PollNonce get(poll_nonce) config(): u32;
     // Challenges.
-    Challenges get(challenges): map u32 => Challenge<T::Hash, T::TokenBalance, T::AccountId, T::Moment>;
+    Challenges get(challenges): map u32 => Challenge<T::Hash, BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>;
     // Polls.
-    Polls get(polls): map u32 => Poll<T::Hash, T::TokenBalance>;
+    Polls get(polls): map u32 => Poll<T::Hash, BalanceOf<T, I>>;
     // Votes.
     // Mapping is between a poll id and a vec of votes.
     // Poll and vote have a 1:n relationship.
-    Votes get(votes): map (u32, T::AccountId) => Vote<T::TokenBalance>;
+    Votes get(votes): map (u32, T::AccountId) => Vote<T::Hash, BalanceOf<T, I>>;
+    // Listings to be resolved automatically, by the block their application expiry or challenge reveal stage end
+    // is expected to have passed in, see `StageClock::blocks_until`.
+    ResolutionQueue get(resolution_queue): map T::BlockNumber => Vec<(RegistryId, u32)>;
+    // Block each listing is scheduled to be resolved in, a listing is scheduled at most once.
+    ScheduledResolution get(scheduled_resolution): map (RegistryId, u32) => Option<T::BlockNumber>;
+    // Stakes each account has reserved in this TCR, by the hash of the listing or proposal they back,
+    // or of the registry they created.
+    // Listing deposits, challenge deposits and unrevealed votes on the same listing add up.
+    Stakes get(stakes): double_map T::AccountId, blake2_256(T::Hash) => BalanceOf<T, I>;
+    // Total stake each account has reserved in this TCR, over all listings and proposals.
+    StakedBalance get(staked_balance): map T::AccountId => BalanceOf<T, I>;
+    // Statuses the listings with a given hash went through, with the moment of each change, by their index.
+    // Kept after the listing is removed, a hash can be listed again.
+    ListingHistory get(listing_history_entry): double_map T::Hash, blake2_256(u32) => Option<(MomentOf<T, I>, ListingStatus)>;
+    // Number of statuses in the history of each listing hash.
+    ListingHistoryLen get(listing_history_len): map T::Hash => u32;
+    // Version of the storage layout, used to migrate storage on runtime upgrades.
+    // 0 - a single registry, listings with a whitelisted flag and a challenge id, stakes locked in the token.
+    // 1 - several registries, listings with a status, stakes reserved in the currency.
+    StorageVersion get(storage_version): u32;
+    // Number of challenges and listings of version 0 migrated so far, challenges first, while the migration runs.
+    MigrationCursor get(migration_cursor): Option<u32>;
+  }
+  add_extra_genesis {
+    // Owner and parameters of the genesis registry.
+    config(owner): T::AccountId;
+    config(min_deposit): BalanceOf<T, I>;
+    config(apply_stage_len): MomentOf<T, I>;
+    config(commit_stage_len): MomentOf<T, I>;
+    config(reveal_stage_len): MomentOf<T, I>;
+    config(dispensation_pct): Permill;
+    // Listings of the genesis registry, whitelisted from the start.
+    // Owner, data and deposit of each listing, the deposit is reserved from the owner.
+    config(initial_listings): Vec<(T::AccountId, Vec<u8>, BalanceOf<T, I>)>;
+    // Admins of the genesis registry, in addition to the owner.
+    config(initial_admins): Vec<T::AccountId>;
+    build(|config: &GenesisConfig<T, I>| {
+      <Registries<T, I>>::insert(0, Registry {
+        owner: config.owner.clone(),
+        min_deposit: config.min_deposit,
+        apply_stage_len: config.apply_stage_len,
+        commit_stage_len: config.commit_stage_len,
+        reveal_stage_len: config.reveal_stage_len,
+        dispensation_pct: config.dispensation_pct,
+      });
+      <RegistryCount<I>>::put(1);
+
+      // The owner is an admin without calling `init`.
+      <Admins<T, I>>::insert((0, config.owner.clone()), true);
+      for admin in &config.initial_admins {
+        <Admins<T, I>>::insert((0, admin.clone()), true);
+      }
+
+      // The currency must be built before the TCR in genesis, to reserve the deposits.
+      for (listing_id, (owner, data, deposit)) in config.initial_listings.iter().enumerate() {
+        assert!(data.len() <= 256, "listing data cannot be more than 256 bytes");
+        assert!(*deposit >= config.min_deposit, "deposit should be more than min_deposit");
+
+        let hashed = <T as system::Trait>::Hashing::hash_of(&(I::PREFIX, 0 as RegistryId, data));
+        assert!(!<Listings<T, I>>::exists(hashed), "Listing already exists");
+        T::Currency::lock(owner, &hashed, *deposit).expect("Initial listing owner cannot lock the deposit.");
+        <Stakes<T, I>>::insert(owner, &hashed, *deposit);
+        <StakedBalance<T, I>>::mutate(owner, |staked| *staked += *deposit);
+
+        let listing_id = listing_id as u32;
+        <Listings<T, I>>::insert(hashed, Listing {
+          id: listing_id,
+          registry_id: 0,
+          data: data.clone(),
+          deposit: *deposit,
+          owner: owner.clone(),
+          application_expiry: Default::default(),
+          status: ListingStatus::Whitelisted,
+        });
+        <ListingIndexHash<T, I>>::insert((0, listing_id), hashed);
+        <Module<T, I>>::record_status(hashed, ListingStatus::Whitelisted);
+      }
+      <ListingCount<I>>::insert(0, config.initial_listings.len() as u32);
+      <Module<T, I>>::endow_account();
+
+      // New chains start with the current storage layout.
+      <StorageVersion<I>>::put(CURRENT_STORAGE_VERSION);
+    });
+  }
+}
+
+// Errors
+// Dispatch errors of the module, listed in the metadata with their index.
+decl_error! {
+  pub enum Error {
+    // Only the owner set in genesis config can initialize the TCR.
+    NotGenesisOwner,
+    // Access denied, admin only.
+    NotAdmin,
+    // The admin is not active.
+    AdminNotActive,
+    // The admin to remove does not exist.
+    AdminNotFound,
+    // The registry does not exist.
+    RegistryNotFound,
+    // The parameters of the genesis registry are governed by the parameterizer, not by its admins.
+    RegistryGoverned,
+    // Listing data cannot be more than 256 bytes.
+    DataTooLong,
+    // The deposit is less than the min deposit of the registry.
+    DepositTooLow,
+    // A listing with the same data already exists.
+    ListingAlreadyExists,
+    // The listing was rejected recently, it can apply again after the cooldown.
+    ReapplyCooldown,
+    // The listing does not exist.
+    ListingNotFound,
+    // Only the listing owner can do this.
+    NotListingOwner,
+    // The listing is not whitelisted.
+    NotWhitelisted,
+    // The listing is already whitelisted.
+    AlreadyWhitelisted,
+    // The listing has an ongoing challenge.
+    AlreadyChallenged,
+    // Not allowed while the listing is challenged.
+    ListingChallenged,
+    // The listing cannot be challenged in its current status.
+    CannotBeChallenged,
+    // Listings cannot be challenged by their owner.
+    OwnListing,
+    // The free balance of the account cannot cover the stake.
+    InsufficientBalance,
+    // The challenge deposit is less than the listing deposit.
+    ChallengeDepositTooLow,
+    // The listing status cannot change to the new status.
+    InvalidStatusTransition,
+    // The withdrawal is more than the listing deposit.
+    WithdrawalExceedsDeposit,
+    // The listing deposit cannot go below the min deposit.
+    DepositBelowMinimum,
+    // The apply stage has passed.
+    ApplyStagePassed,
+    // The apply stage has not passed.
+    ApplyStageNotPassed,
+    // The commit stage has passed.
+    CommitStagePassed,
+    // The commit stage has not passed.
+    CommitStageNotPassed,
+    // The reveal stage has passed.
+    RevealStagePassed,
+    // The reveal stage has not passed.
+    RevealStageNotPassed,
+    // The challenge does not exist.
+    ChallengeNotFound,
+    // The challenge does not belong to this registry.
+    ChallengeNotInRegistry,
+    // The challenge is already resolved.
+    ChallengeAlreadyResolved,
+    // The challenge is not resolved.
+    ChallengeNotResolved,
+    // The account already committed a vote on the challenge.
+    VoteAlreadyCommitted,
+    // The vote does not exist.
+    VoteNotFound,
+    // The vote is already revealed.
+    VoteAlreadyRevealed,
+    // The revealed vote does not match the commit.
+    VoteMismatch,
+    // The vote deposit is more than the winning tokens.
+    VoteDepositExceedsTokens,
+    // The vote reward has already been claimed.
+    RewardAlreadyClaimed,
+    // The unclaimed rewards of the challenge have been burned.
+    RewardsBurned,
+    // The paid rewards are more than the reward pool.
+    RewardsExceedPool,
+    // Nothing is left to burn in the challenge.
+    NothingToBurn,
+    // The stake is no longer reserved.
+    StakeNotReserved,
+    // The TCR account does not hold enough to pay out.
+    InsufficientPot,
+    // The storage of the module is being migrated.
+    StorageMigrating,
+    // Arithmetic overflow.
+    Overflow,
+    // Arithmetic underflow.
+    Underflow,
   }
 }
 
 // Events
 decl_event!(
-  pub enum Event<T> where AccountId = <T as system::Trait>::AccountId, 
-  Balance = <T as token::Trait>::TokenBalance, 
+  pub enum Event<T, I: Instance = DefaultInstance> where AccountId = <T as system::Trait>::AccountId, 
+  Balance = BalanceOf<T, I>, 
   Hash = <T as system::Trait>::Hash {
     // When a listing is proposed.
     Proposed(AccountId, Hash, Balance),
     // When a listing is challenged.
     Challenged(AccountId, Hash, u32, Balance),
-    // When a challenge is voted on.
-    Voted(AccountId, u32, Balance),
+    // When a vote is committed for a challenge.
+    Committed(AccountId, u32, Balance),
+    // When a committed vote is revealed.
+    Revealed(AccountId, u32, bool, Balance),
     // When a challenge is resolved.
     Resolved(Hash, u32),
     // When a listing is accepted in the registry.
@@ -110,70 +564,142 @@ decl_event!(
     Rejected(Hash),
     // When a vote reward is claimed for a challenge.
     Claimed(AccountId, u32),
+    // When a listing owner adds to the listing deposit.
+    Deposited(AccountId, Hash, Balance),
+    // When a listing owner withdraws from the listing deposit.
+    Withdrawn(AccountId, Hash, Balance),
+    // When a listing owner removes the listing from the registry.
+    Exited(AccountId, Hash),
+    // When the rounding dust of a reward pool is swept to the registry owner.
+    DustSwept(AccountId, u32, Balance),
+    // When a new registry is created.
+    RegistryCreated(AccountId, RegistryId),
+    // When an admin changes the parameters of a registry.
+    RegistryConfigured(AccountId, RegistryId),
+    // When the unclaimed rewards and deposits of a challenge are burned.
+    UnclaimedBurned(u32, Balance),
+    // When an account reserves a stake for a listing or proposal.
+    Staked(AccountId, Hash, Balance),
+    // When a stake is returned to the free balance of the account.
+    Unstaked(AccountId, Hash, Balance),
+    // When a stake is forfeited to the TCR account.
+    Forfeited(AccountId, Hash, Balance),
   }
 );
 
 // Module impl
 decl_module! {
-  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+  pub struct Module<T: Trait<I>, I: Instance = DefaultInstance> for enum Call where origin: T::Origin {
+    type Error = Error;
+
     // Initialize events for this module.
     fn deposit_event() = default;
 
     // Initialize the TCR.
-    // Initialize token.
-    // Make sender an admin if it's the owner account set in genesis config.
-    // Owner then has all the tokens and admin rights to the TCR.
-    // They can then distribute tokens in conventional ways.
+    // Make sender an admin of the genesis registry if it's the owner account set in genesis config.
+    // Owner then has admin rights to the genesis registry.
+    // The owner is already an admin since genesis, calling init is no longer required.
     fn init(origin) {
       let sender = ensure_signed(origin)?;
-      ensure!(sender == Self::owner(), "Only the owner set in genesis config can initialize the TCR");
-      <token::Module<T>>::init(sender.clone())?;
-      <Admins<T>>::insert(sender, true);
+      ensure!(sender == Self::registries(0).owner, Error::NotGenesisOwner);
+      <Admins<T, I>>::insert((0, sender), true);
+    }
+
+    // Create a new registry.
+    // The sender becomes the owner and the first admin of the registry.
+    // The registry deposit is reserved from the sender.
+    fn create_registry(origin,
+      #[compact] min_deposit: BalanceOf<T, I>,
+      apply_stage_len: MomentOf<T, I>,
+      commit_stage_len: MomentOf<T, I>,
+      reveal_stage_len: MomentOf<T, I>,
+      dispensation_pct: Permill) -> Result<(), Error> {
+
+      let sender = ensure_signed(origin)?;
+      let deposit = T::RegistryDeposit::get();
+      if !deposit.is_zero() {
+        Self::ensure_can_stake(&sender, deposit)?;
+      }
+
+      let registry_id = Self::registry_count();
+      let next_id = registry_id.checked_add(1).ok_or(Error::Overflow)?;
+
+      if !deposit.is_zero() {
+        Self::reserve_stake(&sender, Self::registry_hash(registry_id), deposit)?;
+      }
+
+      let registry = Registry {
+        owner: sender.clone(),
+        min_deposit,
+        apply_stage_len,
+        commit_stage_len,
+        reveal_stage_len,
+        dispensation_pct,
+      };
+
+      <Registries<T, I>>::insert(registry_id, registry);
+      <RegistryCount<I>>::put(next_id);
+      <Admins<T, I>>::insert((registry_id, sender.clone()), true);
+
+      Self::deposit_event(RawEvent::RegistryCreated(sender, registry_id));
+      print("Registry created!");
+
+      Ok(())
     }
 
-    // Propose a listing on the registry.
+    // Propose a listing on a registry.
     // Takes the listing name (data) as a byte vector.
     // Takes deposit as stake backing the listing.
     // Checks if the stake is less than minimum deposit needed.
-    fn propose(origin, data: Vec<u8>, #[compact] deposit: T::TokenBalance) -> Result {
+    fn propose(origin, registry_id: RegistryId, data: Vec<u8>, #[compact] deposit: BalanceOf<T, I>) -> Result<(), Error> {
       let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+      Self::ensure_can_stake(&sender, deposit)?;
 
       // To avoid byte arrays with unlimited length.
-      ensure!(data.len() <= 256, "listing data cannot be more than 256 bytes");
-
-      let min_deposit = Self::min_deposit().ok_or("Min deposit not set")?;
-      ensure!(deposit >= min_deposit, "deposit should be more than min_deposit");
+      ensure!(data.len() <= 256, Error::DataTooLong);
 
+      let registry = Self::registry(registry_id)?;
+      ensure!(deposit >= registry.min_deposit, Error::DepositTooLow);
       // Set application expiry for the listing.
-      // Using the `Timestamp` SRML module for getting the block timestamp.
-      // Generating a future timestamp by adding the apply stage length.
-      let now = <timestamp::Module<T>>::get();
-      let apply_stage_len = Self::apply_stage_len().ok_or("Apply stage length not set.")?;
-      let app_exp = now.checked_add(&apply_stage_len).ok_or("Overflow when setting application expiry.")?;
+      // Using the configured stage clock, a timestamp or a block number.
+      // Generating a future moment by adding the apply stage length.
+      let now = T::Clock::now();
+      let app_exp = now.checked_add(&registry.apply_stage_len).ok_or(Error::Overflow)?;
 
-      let hashed = <T as system::Trait>::Hashing::hash(&data);
+      // The instance prefix keeps the listings of different TCR instances apart.
+      let hashed = <T as system::Trait>::Hashing::hash_of(&(I::PREFIX, registry_id, &data));
 
-      let listing_id = Self::listing_count();
+      let listing_id = Self::listing_count(registry_id);
 
       // Create a new listing instance and store it.
       let listing = Listing {
         id: listing_id,
+        registry_id,
         data,
         deposit,
         owner: sender.clone(),
-        whitelisted: false,
-        challenge_id: 0,
         application_expiry: app_exp,
+        status: ListingStatus::Applied,
       };
 
-      ensure!(!<Listings<T>>::exists(hashed), "Listing already exists");
+      ensure!(!<Listings<T, I>>::exists(hashed), Error::ListingAlreadyExists);
+
+      // Rejected listings can apply again once the cooldown has passed.
+      if let Some((rejected_at, ListingStatus::Rejected(_))) | Some((rejected_at, ListingStatus::Removed(_))) =
+        Self::last_status(hashed) {
+        let cooldown_ends = rejected_at.checked_add(&T::ReapplyCooldown::get()).ok_or(Error::Overflow)?;
+        ensure!(cooldown_ends <= now, Error::ReapplyCooldown);
+      }
 
       // Deduct the deposit for application.
-      <token::Module<T>>::lock(sender.clone(), deposit, hashed.clone())?;
+      Self::reserve_stake(&sender, hashed, deposit)?;
 
-      <ListingCount>::put(listing_id + 1);
-      <Listings<T>>::insert(hashed, listing);
-      <ListingIndexHash<T>>::insert(listing_id, hashed);
+      <ListingCount<I>>::insert(registry_id, listing_id + 1);
+      <Listings<T, I>>::insert(hashed, listing);
+      <ListingIndexHash<T, I>>::insert((registry_id, listing_id), hashed);
+      Self::record_status(hashed, ListingStatus::Applied);
+      Self::schedule_resolution(app_exp, registry_id, listing_id);
 
       // Let the world know.
       // Raise the event.
@@ -184,271 +710,436 @@ decl_module! {
     }
 
     // Challenge a listing.
-    // For simplicity, only three checks are being done.
+    // Applications can be challenged during their apply stage,
+    // whitelisted listings can be challenged at any time.
+    // For simplicity, only three other checks are being done.
     //    a. If the listing exists.
     //    c. If the challenger is not the owner of the listing.
     //    b. If enough deposit is sent for challenge.
-    fn challenge(origin, listing_id: u32, #[compact] deposit: T::TokenBalance) -> Result {
+    fn challenge(origin, registry_id: RegistryId, listing_id: u32, #[compact] deposit: BalanceOf<T, I>) -> Result<(), Error> {
       let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+      Self::ensure_can_stake(&sender, deposit)?;
 
-      ensure!(<ListingIndexHash<T>>::exists(listing_id), "Listing not found.");
+      ensure!(<ListingIndexHash<T, I>>::exists((registry_id, listing_id)), Error::ListingNotFound);
 
-      let listing_hash = Self::index_hash(listing_id);
+      let listing_hash = Self::index_hash((registry_id, listing_id));
       let listing = Self::listings(listing_hash);
 
-      ensure!(listing.challenge_id == 0, "Listing is already challenged.");
-      ensure!(listing.owner != sender, "You cannot challenge your own listing.");
-      ensure!(deposit >= listing.deposit, "Not enough deposit to challenge.");
+      ensure!(listing.status.challenge_id().is_none(), Error::AlreadyChallenged);
+      ensure!(listing.owner != sender, Error::OwnListing);
+      ensure!(deposit >= listing.deposit, Error::ChallengeDepositTooLow);
 
-      // Get current time.
-      let now = <timestamp::Module<T>>::get();
-
-      // Get commit stage length.
-      let commit_stage_len = Self::commit_stage_len().ok_or("Commit stage length not set.")?;
-      let voting_exp = now.checked_add(&commit_stage_len).ok_or("Overflow when setting voting expiry.")?;
-
-      // Check apply stage length not passed.
+      // Check apply stage length not passed, unless the listing is whitelisted.
       // Ensure listing.application_expiry < now.
-      ensure!(listing.application_expiry > now, "Apply stage length has passed.");
-
-      let challenge = Challenge {
-        listing_hash,
-        deposit,
-        owner: sender.clone(),
-        voting_ends: voting_exp,
-        resolved: false,
-        reward_pool: 0u32.into(),
-        total_tokens: 0u32.into(),
-      };
-
-      let poll = Poll {
-        listing_hash,
-        votes_for: listing.deposit,
-        votes_against: deposit,
-        passed: false,
-      };
+      let now = T::Clock::now();
+      let whitelisted = listing.status.is_whitelisted();
+      ensure!(whitelisted || listing.application_expiry > now, Error::ApplyStagePassed);
 
-      // Deduct the deposit for challenge.
-      <token::Module<T>>::lock(sender.clone(), deposit, listing_hash)?;
+      // The new challenge gets the current poll nonce as its id.
+      let status = ListingStatus::Challenged { challenge_id: Self::poll_nonce(), whitelisted };
+      ensure!(listing.status.can_become(&status), Error::CannotBeChallenged);
 
-      // Global poll nonce.
-      // Helps keep the count of challenges and in maping votes.
-      let poll_nonce = <PollNonce>::get();
-
-      // Add a new challenge and the corresponding poll in the respective collections.
-      <Challenges<T>>::insert(poll_nonce, challenge);
-      <Polls<T>>::insert(poll_nonce, poll);
+      // Create the challenge and deduct the deposit for it.
+      let challenge_id = Self::new_challenge(registry_id, sender.clone(), listing_hash, deposit)?;
 
       // Update listing with challenge id.
-      <Listings<T>>::mutate(listing_hash, |listing| {
-        listing.challenge_id = poll_nonce;
-      });
-
-      // Update the poll nonce.
-      <PollNonce>::put(poll_nonce + 1);
+      Self::set_status(listing_hash, &listing, status)?;
+      Self::schedule_resolution(Self::challenges(challenge_id).reveal_ends, registry_id, listing_id);
 
       // Raise the event.
-      Self::deposit_event(RawEvent::Challenged(sender, listing_hash, poll_nonce, deposit));
+      Self::deposit_event(RawEvent::Challenged(sender, listing_hash, challenge_id, deposit));
       print("Challenge created!");
 
       Ok(())
     }
 
-    // Registers a vote for a particular challenge.
+    // Commits a vote for a particular challenge.
     // Checks if the listing is challenged, and
     // if the commit stage length has not passed.
-    // To keep it simple, we just store the choice as a bool - true: aye; false: nay.
-    fn vote(origin, challenge_id: u32, value: bool, #[compact] deposit: T::TokenBalance) -> Result {
+    // The vote value is not stored, only the hash of the value and a salt.
+    // Use `commit_hash` to generate the `secret_hash` off-chain, it is bound to the challenge and the voter.
+    fn commit_vote(origin, registry_id: RegistryId, challenge_id: u32, secret_hash: T::Hash, #[compact] deposit: BalanceOf<T, I>) -> Result<(), Error> {
       let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+      Self::ensure_can_stake(&sender, deposit)?;
 
       // Check if listing is challenged.
-      ensure!(<Challenges<T>>::exists(challenge_id), "Challenge does not exist.");
+      ensure!(<Challenges<T, I>>::exists(challenge_id), Error::ChallengeNotFound);
       let challenge = Self::challenges(challenge_id);
-      ensure!(challenge.resolved == false, "Challenge is already resolved.");
+      ensure!(challenge.registry_id == registry_id, Error::ChallengeNotInRegistry);
+      ensure!(challenge.resolved == false, Error::ChallengeAlreadyResolved);
 
       // Check commit stage length not passed.
-      let now = <timestamp::Module<T>>::get();
-      ensure!(challenge.voting_ends > now, "Commit stage length has passed.");
+      let now = T::Clock::now();
+      ensure!(challenge.voting_ends > now, Error::CommitStagePassed);
 
-      // Deduct the deposit for vote.
-      <token::Module<T>>::lock(sender.clone(), deposit, challenge.listing_hash)?;
+      // One vote per account per challenge.
+      ensure!(!<Votes<T, I>>::exists((challenge_id, sender.clone())), Error::VoteAlreadyCommitted);
 
-      let mut poll_instance = Self::polls(challenge_id);
-      // Based on vote value, increase the count of votes (for or against).
-      match value {
-        true => poll_instance.votes_for += deposit,
-        false => poll_instance.votes_against += deposit,
-      }
+      // Deduct the deposit for vote.
+      Self::reserve_stake(&sender, challenge.listing_hash, deposit)?;
 
       // Create a new vote instance with the input params.
+      // Value is set when the vote is revealed.
       let vote_instance = Vote {
-        value,
+        secret_hash,
+        value: false,
         deposit,
+        revealed: false,
         claimed: false,
       };
 
-      // Mutate polls collection to update the poll instance.
-      <Polls<T>>::mutate(challenge_id, |poll| *poll = poll_instance);
-
       // Insert new vote into votes collection.
-      <Votes<T>>::insert((challenge_id, sender.clone()), vote_instance);
+      <Votes<T, I>>::insert((challenge_id, sender.clone()), vote_instance);
 
       // Raise the event.
-      Self::deposit_event(RawEvent::Voted(sender, challenge_id, deposit));
-      print("Vote created!");
+      Self::deposit_event(RawEvent::Committed(sender, challenge_id, deposit));
+      print("Vote committed!");
       Ok(())
     }
 
-    // Resolves the status of a listing.
-    // Changes the value of whitelisted to either true or false.
-    // Checks if the listing is challenged or not.
-    // Further checks if apply stage or commit stage has passed.
-    // Compares if votes are in favour of whitelisting.
-    // Updates the listing status.
-    fn resolve(_origin, listing_id: u32) -> Result {
-      ensure!(<ListingIndexHash<T>>::exists(listing_id), "Listing not found.");
-
-      let listing_hash = Self::index_hash(listing_id);
-      let listing = Self::listings(listing_hash);
-
-      let now = <timestamp::Module<T>>::get();
-      let challenge;
-      let poll;
-
-      // Check if listing is challenged.
-      if listing.challenge_id > 0 {
-        // Challenge.
-        challenge = Self::challenges(listing.challenge_id);
-        poll = Self::polls(listing.challenge_id);
+    // Reveals a committed vote.
+    // Checks if the commit stage has passed and the reveal stage has not.
+    // The value and salt must hash to the committed `secret_hash`.
+    // To keep it simple, we just store the choice as a bool - true: aye; false: nay.
+    fn reveal_vote(origin, registry_id: RegistryId, challenge_id: u32, value: bool, salt: T::Hash) -> Result<(), Error> {
+      let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
 
-        // Check commit stage length has passed.
-        ensure!(challenge.voting_ends < now, "Commit stage length has not passed.");
-      } else {
-        // No challenge.
-        // Check if apply stage length has passed.
-        ensure!(listing.application_expiry < now, "Apply stage length has not passed.");
+      ensure!(<Challenges<T, I>>::exists(challenge_id), Error::ChallengeNotFound);
+      let challenge = Self::challenges(challenge_id);
+      ensure!(challenge.registry_id == registry_id, Error::ChallengeNotInRegistry);
+      ensure!(challenge.resolved == false, Error::ChallengeAlreadyResolved);
 
-        // Update listing status.
-        <Listings<T>>::mutate(listing_hash, |listing|
-        {
-          listing.whitelisted = true;
-        });
+      // Check commit stage has passed and reveal stage has not.
+      let now = T::Clock::now();
+      ensure!(challenge.voting_ends <= now, Error::CommitStageNotPassed);
+      ensure!(challenge.reveal_ends > now, Error::RevealStagePassed);
 
-        Self::deposit_event(RawEvent::Accepted(listing_hash));
-        return Ok(());
-      }
+      let vote = Self::stored_vote(challenge_id, &sender).ok_or(Error::VoteNotFound)?;
+      ensure!(vote.revealed == false, Error::VoteAlreadyRevealed);
+      ensure!(Self::commit_hash(challenge_id, &sender, value, salt) == vote.secret_hash, Error::VoteMismatch);
 
-      let mut whitelisted = false;
+      // A revealed vote is at stake in the poll.
+      // Winning votes get their deposit back with the reward when claiming.
+      Self::forfeit(&sender, challenge.listing_hash, vote.deposit)?;
 
-      // Mutate polls collection to update the poll instance.
-      <Polls<T>>::mutate(listing.challenge_id, |poll| {
-        if poll.votes_for >= poll.votes_against {
-            poll.passed = true;
-            whitelisted = true;
-        } else {
-            poll.passed = false;
+      // Based on vote value, increase the count of votes (for or against).
+      <Polls<T, I>>::mutate(challenge_id, |poll| {
+        match value {
+          true => poll.votes_for += vote.deposit,
+          false => poll.votes_against += vote.deposit,
         }
       });
 
-      // Update listing status.
-      <Listings<T>>::mutate(listing_hash, |listing| {
-        listing.whitelisted = whitelisted;
-        listing.challenge_id = 0;
+      <Votes<T, I>>::mutate((challenge_id, sender.clone()), |vote| {
+        vote.value = value;
+        vote.revealed = true;
       });
 
-      // Update challenge.
-      <Challenges<T>>::mutate(listing.challenge_id, |challenge| {
-        challenge.resolved = true;
-        if whitelisted == true {
-          challenge.total_tokens = poll.votes_for;
-          challenge.reward_pool = challenge.deposit + poll.votes_against;
-        } else {
-          challenge.total_tokens = poll.votes_against;
-          challenge.reward_pool = listing.deposit + poll.votes_for;
-        }
-      });
+      // Raise the event.
+      Self::deposit_event(RawEvent::Revealed(sender, challenge_id, value, vote.deposit));
+      print("Vote revealed!");
+      Ok(())
+    }
 
-      // Raise appropriate event as per whitelisting status.
-      if whitelisted == true {
-        Self::deposit_event(RawEvent::Accepted(listing_hash));
-      } else {
-        // If rejected, give challenge deposit back to the challenger.
-        <token::Module<T>>::unlock(challenge.owner, challenge.deposit, listing_hash)?;
-        Self::deposit_event(RawEvent::Rejected(listing_hash));
+    // Resolves the status of a listing.
+    // Listings are also resolved automatically at the end of the block,
+    // see `on_finalize`, this call is for resolving them right away.
+    fn resolve(_origin, registry_id: RegistryId, listing_id: u32) -> Result<(), Error> {
+      Self::do_resolve(registry_id, listing_id)
+    }
+
+    fn on_initialize(_n: T::BlockNumber) {
+      if Self::storage_version() < CURRENT_STORAGE_VERSION {
+        Self::migrate_from_v0();
       }
+    }
 
-      Self::deposit_event(RawEvent::Resolved(listing_hash, listing.challenge_id));
-      Ok(())
+    // Resolves the listings scheduled in this block whose apply stage or challenge reveal stage has passed.
+    // At most `MaxResolutionsPerBlock` listings are looked at per block, to keep the weight predictable.
+    // The rest move to the front of the next block in a single write, and listings that are not due yet
+    // to the block they are expected to be due in.
+    // A listing that fails to resolve stays scheduled, at the back of the next block.
+    // Listings are unscheduled when they are resolved manually or challenged, so no entry is stale.
+    fn on_finalize(n: T::BlockNumber) {
+      let mut scheduled = <ResolutionQueue<T, I>>::take(n);
+      if scheduled.is_empty() {
+        return;
+      }
+
+      let now = T::Clock::now();
+      let max = T::MaxResolutionsPerBlock::get() as usize;
+      let mut deferred = if scheduled.len() > max { scheduled.split_off(max) } else { Vec::new() };
+      let mut failed = Vec::new();
+      for (registry_id, listing_id) in scheduled {
+        <ScheduledResolution<T, I>>::remove((registry_id, listing_id));
+        let deadline = match Self::resolution_deadline(registry_id, listing_id) {
+          Some(deadline) => deadline,
+          None => continue,
+        };
+
+        if deadline >= now {
+          Self::schedule_resolution(deadline, registry_id, listing_id);
+        } else if let Err(e) = Self::do_resolve(registry_id, listing_id) {
+          print(<&'static str>::from(e));
+          failed.push((registry_id, listing_id));
+        }
+      }
+
+      if deferred.is_empty() && failed.is_empty() {
+        return;
+      }
+      let next = n.saturating_add(One::one());
+      for &entry in deferred.iter().chain(failed.iter()) {
+        <ScheduledResolution<T, I>>::insert(entry, next);
+      }
+      <ResolutionQueue<T, I>>::mutate(next, |queue| {
+        deferred.append(queue);
+        deferred.append(&mut failed);
+        *queue = deferred;
+      });
     }
 
     // Claim reward for a vote.
-    fn claim_reward(origin, challenge_id: u32) -> Result {
+    // Votes that were never revealed are not counted,
+    // their deposit is returned without any reward.
+    fn claim_reward(origin, registry_id: RegistryId, challenge_id: u32) -> Result<(), Error> {
       let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
 
       // Ensure challenge exists and has been resolved.
-      ensure!(<Challenges<T>>::exists(challenge_id), "Challenge not found.");
+      ensure!(<Challenges<T, I>>::exists(challenge_id), Error::ChallengeNotFound);
       let challenge = Self::challenges(challenge_id);
-      ensure!(challenge.resolved == true, "Challenge is not resolved.");
+      ensure!(challenge.registry_id == registry_id, Error::ChallengeNotInRegistry);
+      ensure!(challenge.resolved == true, Error::ChallengeNotResolved);
 
       // Get the poll and vote instances.
       // Reward depends on poll passed status and vote value.
       let poll = Self::polls(challenge_id);
-      let vote = Self::votes((challenge_id, sender.clone()));
+      let vote = Self::stored_vote(challenge_id, &sender).ok_or(Error::VoteNotFound)?;
 
       // Ensure vote reward is not already claimed.
-      ensure!(vote.claimed == false, "Vote reward has already been claimed.");
+      ensure!(vote.claimed == false, Error::RewardAlreadyClaimed);
+
+      if vote.revealed == false {
+        // Unrevealed vote, return the deposit.
+        Self::release_stake(&sender, challenge.listing_hash, vote.deposit)?;
+      } else if poll.passed == vote.value {
+        ensure!(challenge.claimed_tokens < challenge.total_tokens, Error::RewardsBurned);
+
+        // If winning party, calculate reward and transfer.
+        let reward = Self::calculate_reward(vote.deposit, challenge.reward_pool, challenge.total_tokens)?;
+        let total = reward.checked_add(&vote.deposit).ok_or(Error::Overflow)?;
+        let claimed_tokens = challenge.claimed_tokens.checked_add(&vote.deposit).ok_or(Error::Overflow)?;
+        let paid_rewards = challenge.paid_rewards.checked_add(&reward).ok_or(Error::Overflow)?;
+
+        // Once every winning vote has claimed, whatever is left in the reward pool is rounding dust.
+        // It is swept to the registry owner.
+        let dust = if claimed_tokens == challenge.total_tokens {
+          challenge.reward_pool.checked_sub(&paid_rewards).ok_or(Error::RewardsExceedPool)?
+        } else {
+          Zero::zero()
+        };
+
+        // The TCR account must cover both payments before either is made.
+        let payable = total.checked_add(&dust).ok_or(Error::Overflow)?;
+        ensure!(T::Currency::free_balance(&Self::account_id()) >= payable, Error::InsufficientPot);
 
-      // If winning party, calculate reward and transfer.
-      if poll.passed == vote.value {
-            let reward_ratio = challenge.reward_pool.checked_div(&challenge.total_tokens).ok_or("overflow in calculating reward")?;
-            let reward = reward_ratio.checked_mul(&vote.deposit).ok_or("overflow in calculating reward")?;
-            let total = reward.checked_add(&vote.deposit).ok_or("overflow in calculating reward")?;
-            <token::Module<T>>::unlock(sender.clone(), total, challenge.listing_hash)?;
+        T::Currency::transfer(&Self::account_id(), &sender, total)?;
 
-            Self::deposit_event(RawEvent::Claimed(sender.clone(), challenge_id));
+        <Challenges<T, I>>::mutate(challenge_id, |challenge| {
+          challenge.claimed_tokens = claimed_tokens;
+          challenge.paid_rewards = paid_rewards;
+        });
+
+        Self::deposit_event(RawEvent::Claimed(sender.clone(), challenge_id));
+
+        if !dust.is_zero() {
+          let owner = Self::registries(registry_id).owner;
+          T::Currency::transfer(&Self::account_id(), &owner, dust)?;
+          Self::deposit_event(RawEvent::DustSwept(owner, challenge_id, dust));
         }
+      }
+
+      // Update vote reward claimed status.
+      <Votes<T, I>>::insert((challenge_id, sender), Vote { claimed: true, ..vote });
+
+      Ok(())
+    }
+
+    // Burn the rewards and deposits that winning voters of a resolved challenge have not claimed.
+    // Forfeited stakes are otherwise kept in the TCR account until they are claimed.
+    // Only root can close the claims of a challenge, the remaining winning votes can no longer claim.
+    fn burn_unclaimed(origin, registry_id: RegistryId, challenge_id: u32) -> Result<(), Error> {
+      ensure_root(origin)?;
+      Self::ensure_migrated()?;
+
+      ensure!(<Challenges<T, I>>::exists(challenge_id), Error::ChallengeNotFound);
+      let challenge = Self::challenges(challenge_id);
+      ensure!(challenge.registry_id == registry_id, Error::ChallengeNotInRegistry);
+      ensure!(challenge.resolved == true, Error::ChallengeNotResolved);
+      ensure!(challenge.claimed_tokens < challenge.total_tokens, Error::NothingToBurn);
+
+      let unclaimed_tokens = challenge.total_tokens.checked_sub(&challenge.claimed_tokens).ok_or(Error::Underflow)?;
+      let unpaid_rewards = challenge.reward_pool.checked_sub(&challenge.paid_rewards).ok_or(Error::RewardsExceedPool)?;
+      let amount = unclaimed_tokens.checked_add(&unpaid_rewards).ok_or(Error::Overflow)?;
+
+      // Dropping the imbalance burns the tokens, the total issuance of the currency goes down.
+      // The TCR account is kept alive, it only holds what it was endowed with besides the stakes.
+      let burned = T::Currency::withdraw(&Self::account_id(), amount, WithdrawReasons::all(), ExistenceRequirement::KeepAlive)?;
+      drop(burned);
+
+      <Challenges<T, I>>::mutate(challenge_id, |challenge| {
+        challenge.claimed_tokens = challenge.total_tokens;
+        challenge.paid_rewards = challenge.reward_pool;
+      });
+
+      Self::deposit_event(RawEvent::UnclaimedBurned(challenge_id, amount));
+      Ok(())
+    }
+
+    // Add to the deposit backing a listing.
+    // Only the listing owner can top up the stake.
+    // Not allowed while the listing is challenged, the challenge is settled with the deposit it was raised against.
+    fn deposit(origin, registry_id: RegistryId, listing_id: u32, #[compact] amount: BalanceOf<T, I>) -> Result<(), Error> {
+      let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+      Self::ensure_can_stake(&sender, amount)?;
+
+      ensure!(<ListingIndexHash<T, I>>::exists((registry_id, listing_id)), Error::ListingNotFound);
+
+      let listing_hash = Self::index_hash((registry_id, listing_id));
+      let listing = Self::listings(listing_hash);
+
+      ensure!(listing.owner == sender, Error::NotListingOwner);
+      ensure!(listing.status.challenge_id().is_none(), Error::ListingChallenged);
+      let updated_deposit = listing.deposit.checked_add(&amount).ok_or(Error::Overflow)?;
+
+      Self::reserve_stake(&sender, listing_hash, amount)?;
+
+      <Listings<T, I>>::mutate(listing_hash, |listing| {
+        listing.deposit = updated_deposit;
+      });
+
+      Self::deposit_event(RawEvent::Deposited(sender, listing_hash, amount));
+      Ok(())
+    }
+
+    // Withdraw from the deposit backing a listing.
+    // The remaining deposit cannot go below the minimum deposit.
+    // Not allowed while the listing is challenged.
+    fn withdraw(origin, registry_id: RegistryId, listing_id: u32, #[compact] amount: BalanceOf<T, I>) -> Result<(), Error> {
+      let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+
+      ensure!(<ListingIndexHash<T, I>>::exists((registry_id, listing_id)), Error::ListingNotFound);
+
+      let listing_hash = Self::index_hash((registry_id, listing_id));
+      let listing = Self::listings(listing_hash);
 
-        // Update vote reward claimed status.
-        <Votes<T>>::mutate((challenge_id, sender), |vote| vote.claimed = true);
+      ensure!(listing.owner == sender, Error::NotListingOwner);
+      ensure!(listing.status.challenge_id().is_none(), Error::ListingChallenged);
 
+      let min_deposit = Self::registries(registry_id).min_deposit;
+      let updated_deposit = listing.deposit.checked_sub(&amount).ok_or(Error::WithdrawalExceedsDeposit)?;
+      ensure!(updated_deposit >= min_deposit, Error::DepositBelowMinimum);
+
+      Self::release_stake(&sender, listing_hash, amount)?;
+
+      <Listings<T, I>>::mutate(listing_hash, |listing| {
+        listing.deposit = updated_deposit;
+      });
+
+      Self::deposit_event(RawEvent::Withdrawn(sender, listing_hash, amount));
+      Ok(())
+    }
+
+    // Exit the registry.
+    // Only whitelisted listings without an ongoing challenge can exit.
+    // Removes the listing and returns the full deposit to the owner.
+    fn exit(origin, registry_id: RegistryId, listing_id: u32) -> Result<(), Error> {
+      let sender = ensure_signed(origin)?;
+      Self::ensure_migrated()?;
+
+      ensure!(<ListingIndexHash<T, I>>::exists((registry_id, listing_id)), Error::ListingNotFound);
+
+      let listing_hash = Self::index_hash((registry_id, listing_id));
+      let listing = Self::listings(listing_hash);
+
+      ensure!(listing.owner == sender, Error::NotListingOwner);
+      ensure!(listing.status.is_whitelisted(), Error::NotWhitelisted);
+      ensure!(listing.status.challenge_id().is_none(), Error::ListingChallenged);
+
+      // Remove the listing and return the deposit to the owner.
+      ensure!(Self::has_stake(&sender, listing_hash, listing.deposit), Error::StakeNotReserved);
+      Self::set_status(listing_hash, &listing, ListingStatus::Exited)?;
+      Self::release_stake(&sender, listing_hash, listing.deposit)?;
+
+      Self::deposit_event(RawEvent::Exited(sender, listing_hash));
+      print("Listing exited!");
       Ok(())
     }
 
-    // Sets the TCR parameters.
-    // Currently only min deposit, apply stage length and commit stage length are supported.
-    // Only admins can set config.
+    // Sets the TCR parameters of a registry.
+    // Currently min deposit, stage lengths and dispensation percentage are supported.
+    // Parameters are governed by token holders through the parameterizer module.
+    // This is only available to the root origin (sudo), for dev chains.
     // Repeated setting just overrides, for simplicity.
     fn set_config(origin,
-      min_deposit: T::TokenBalance,
-      apply_stage_len: T::Moment,
-      commit_stage_len: T::Moment) -> Result {
+      registry_id: RegistryId,
+      min_deposit: BalanceOf<T, I>,
+      apply_stage_len: MomentOf<T, I>,
+      commit_stage_len: MomentOf<T, I>,
+      reveal_stage_len: MomentOf<T, I>,
+      dispensation_pct: Permill) -> Result<(), Error> {
 
-      Self::ensure_admin(origin)?;
+      ensure_root(origin)?;
+      ensure!(<Registries<T, I>>::exists(registry_id), Error::RegistryNotFound);
 
-      <MinDeposit<T>>::put(min_deposit);
-      <ApplyStageLen<T>>::put(apply_stage_len);
-      <CommitStageLen<T>>::put(commit_stage_len);
+      Self::update_registry(registry_id, min_deposit, apply_stage_len, commit_stage_len, reveal_stage_len, dispensation_pct);
+      Ok(())
+    }
 
+    // Sets the TCR parameters of a registry created with `create_registry`, by one of its admins.
+    // The genesis registry is governed by token holders through the parameterizer module instead.
+    // Changes apply to new listings and challenges, ongoing stages keep their deadlines.
+    fn set_registry_config(origin,
+      registry_id: RegistryId,
+      #[compact] min_deposit: BalanceOf<T, I>,
+      apply_stage_len: MomentOf<T, I>,
+      commit_stage_len: MomentOf<T, I>,
+      reveal_stage_len: MomentOf<T, I>,
+      dispensation_pct: Permill) -> Result<(), Error> {
+
+      let sender = ensure_signed(origin.clone())?;
+      ensure!(registry_id != 0, Error::RegistryGoverned);
+      ensure!(<Registries<T, I>>::exists(registry_id), Error::RegistryNotFound);
+      Self::ensure_admin(origin, registry_id)?;
+
+      Self::update_registry(registry_id, min_deposit, apply_stage_len, commit_stage_len, reveal_stage_len, dispensation_pct);
+      Self::deposit_event(RawEvent::RegistryConfigured(sender, registry_id));
       Ok(())
     }
 
-    // Add a new admin for the TCR.
+    // Add a new admin for a registry.
     // Admins can do specific operations.
-    // Set config.
-    fn add_admin(origin, new_admin: T::AccountId) -> Result {
-      Self::ensure_admin(origin)?;
+    // Manage the admins of the registry.
+    fn add_admin(origin, registry_id: RegistryId, new_admin: T::AccountId) -> Result<(), Error> {
+      Self::ensure_admin(origin, registry_id)?;
 
-      <Admins<T>>::insert(new_admin, true);
+      <Admins<T, I>>::insert((registry_id, new_admin), true);
       print("New admin added!");
       Ok(())
     }
 
     // Remove an admin.
-    fn remove_admin(origin, admin_to_remove: T::AccountId) -> Result {
-      Self::ensure_admin(origin)?;
+    fn remove_admin(origin, registry_id: RegistryId, admin_to_remove: T::AccountId) -> Result<(), Error> {
+      Self::ensure_admin(origin, registry_id)?;
 
-      ensure!(<Admins<T>>::exists(&admin_to_remove), "The admin you are trying to remove does not exist");
-      <Admins<T>>::remove(admin_to_remove);
+      ensure!(<Admins<T, I>>::exists((registry_id, admin_to_remove.clone())), Error::AdminNotFound);
+      <Admins<T, I>>::remove((registry_id, admin_to_remove));
       print("Admin removed!");
       Ok(())
     }
@@ -456,105 +1147,1075 @@ decl_module! {
 }
 
 // Utility and private functions.
-impl<T: Trait> Module<T> {
-  // Ensure that a user is an admin.
-  fn ensure_admin(origin: T::Origin) -> Result {
+impl<T: Trait<I>, I: Instance> Module<T, I> {
+  // Ensure that a user is an admin of a registry.
+  fn ensure_admin(origin: T::Origin, registry_id: RegistryId) -> Result<(), Error> {
     let sender = ensure_signed(origin)?;
 
-    ensure!(<Admins<T>>::exists(&sender), "Access denied. Admin only.");
-    ensure!(Self::admins(sender) == true, "Admin is not active");
+    ensure!(<Admins<T, I>>::exists((registry_id, sender.clone())), Error::NotAdmin);
+    ensure!(Self::admins((registry_id, sender)) == true, Error::AdminNotActive);
 
     Ok(())
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+  // Hash the registry deposit is staked for, distinct from the listing hashes of the registry.
+  pub fn registry_hash(registry_id: RegistryId) -> T::Hash {
+    <T as system::Trait>::Hashing::hash_of(&(I::PREFIX, registry_id))
+  }
 
-  use primitives::{Blake2Hasher, H256};
-  use runtime_io::with_externalities;
-  use runtime_primitives::{
-    testing::{Digest, DigestItem, Header, UintAuthorityId},
-    traits::{BlakeTwo256, IdentityLookup},
-    BuildStorage,
-  };
-  use support::{assert_noop, assert_ok, impl_outer_origin};
+  // Overrides the parameters of a registry.
+  fn update_registry(
+    registry_id: RegistryId,
+    min_deposit: BalanceOf<T, I>,
+    apply_stage_len: MomentOf<T, I>,
+    commit_stage_len: MomentOf<T, I>,
+    reveal_stage_len: MomentOf<T, I>,
+    dispensation_pct: Permill,
+  ) {
+    <Registries<T, I>>::mutate(registry_id, |registry| {
+      registry.min_deposit = min_deposit;
+      registry.apply_stage_len = apply_stage_len;
+      registry.commit_stage_len = commit_stage_len;
+      registry.reveal_stage_len = reveal_stage_len;
+      registry.dispensation_pct = dispensation_pct;
+    });
+  }
 
-  impl_outer_origin! {
-    pub enum Origin for Test {}
+  // Account holding the forfeited stakes of this TCR instance.
+  pub fn account_id() -> T::AccountId {
+    MODULE_ID.into_sub_account(I::PREFIX)
   }
 
-  // For testing the module, we construct most of a mock runtime. This means
-  // first constructing a configuration type (`Test`) which `impl`s each of the
-  // configuration traits of modules we want to use.
-  #[derive(Clone, Eq, PartialEq)]
-  pub struct Test;
-  impl system::Trait for Test {
-    type Origin = Origin;
-    type Index = u64;
-    type BlockNumber = u64;
-    type Hash = H256;
-    type Hashing = BlakeTwo256;
-    type Digest = Digest;
-    type AccountId = u64;
-    type Lookup = IdentityLookup<u64>;
-    type Header = Header;
-    type Event = ();
-    type Log = DigestItem;
+  // Endows the TCR account with the minimum balance of the currency, if it does not exist yet.
+  // Currencies like `balances` only move reserved funds to accounts that exist,
+  // so without it the first forfeited stake could not be moved.
+  // The endowment is never paid out, it keeps the account alive.
+  fn endow_account() {
+    let account_id = Self::account_id();
+    let minimum_balance = T::Currency::minimum_balance();
+    if !minimum_balance.is_zero() && T::Currency::total_balance(&account_id).is_zero() {
+      drop(T::Currency::deposit_creating(&account_id, minimum_balance));
+    }
   }
-  impl consensus::Trait for Test {
-    type Log = DigestItem;
-    type SessionKey = UintAuthorityId;
-    type InherentOfflineReport = ();
+
+  // Whether an account has a stake of at least `value` reserved for a listing or proposal.
+  fn has_stake(who: &T::AccountId, subject_hash: T::Hash, value: BalanceOf<T, I>) -> bool {
+    Self::stakes(who, &subject_hash) >= value && T::Currency::reserved_balance(who) >= value
   }
-  impl token::Trait for Test {
-    type Event = ();
-    type TokenBalance = u64;
+
+  // Ensures the storage of the module has been migrated, listings and challenges cannot change until then.
+  fn ensure_migrated() -> Result<(), Error> {
+    ensure!(Self::storage_version() == CURRENT_STORAGE_VERSION, Error::StorageMigrating);
+    Ok(())
   }
-  impl timestamp::Trait for Test {
-    type Moment = u64;
-    type OnTimestampSet = ();
+
+  // Ensures an account can lock a stake.
+  // Every call that stakes checks this first, before anything is written.
+  pub(crate) fn ensure_can_stake(who: &T::AccountId, value: BalanceOf<T, I>) -> Result<(), Error> {
+    ensure!(T::Currency::can_lock(who, value), Error::InsufficientBalance);
+    Ok(())
   }
-  impl Trait for Test {
-    type Event = ();
+
+  // Reserves a stake of an account for a listing or proposal.
+  // Callers reserve after all their other checks and before writing anything,
+  // so a stake the account cannot cover fails the call without a trace.
+  pub(crate) fn reserve_stake(who: &T::AccountId, subject_hash: T::Hash, value: BalanceOf<T, I>) -> Result<(), Error> {
+    let stake = Self::stakes(who, &subject_hash).checked_add(&value).ok_or(Error::Overflow)?;
+    let staked = Self::staked_balance(who).checked_add(&value).ok_or(Error::Overflow)?;
+    T::Currency::lock(who, &subject_hash, value).map_err(|_| Error::InsufficientBalance)?;
+
+    <Stakes<T, I>>::insert(who, &subject_hash, stake);
+    <StakedBalance<T, I>>::insert(who, staked);
+    Self::deposit_event(RawEvent::Staked(who.clone(), subject_hash, value));
+    Ok(())
   }
-  type Tcr = Module<Test>;
-  type Token = token::Module<Test>;
 
-  // Builds the genesis config store and sets mock values.
-  fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-    let mut t = system::GenesisConfig::<Test>::default()
-      .build_storage()
-      .unwrap()
-      .0;
-    t.extend(
-      token::GenesisConfig::<Test> { total_supply: 1000 }
-        .build_storage()
-        .unwrap()
-        .0,
-    );
-    t.extend(
-      GenesisConfig::<Test> {
-        owner: 1,
-        min_deposit: 100,
-        apply_stage_len: 10,
-        commit_stage_len: 10,
-        poll_nonce: 1,
+  // Returns a stake to the free balance of the account.
+  pub(crate) fn release_stake(who: &T::AccountId, subject_hash: T::Hash, value: BalanceOf<T, I>) -> Result<(), Error> {
+    ensure!(Self::has_stake(who, subject_hash, value), Error::StakeNotReserved);
+    T::Currency::unlock(who, &subject_hash, value)?;
+    Self::take_stake(who, subject_hash, value)?;
+
+    Self::deposit_event(RawEvent::Unstaked(who.clone(), subject_hash, value));
+    Ok(())
+  }
+
+  // Moves a stake to the TCR account, where it is paid out from.
+  fn forfeit(who: &T::AccountId, subject_hash: T::Hash, value: BalanceOf<T, I>) -> Result<(), Error> {
+    ensure!(Self::has_stake(who, subject_hash, value), Error::StakeNotReserved);
+    T::Currency::repatriate_locked(who, &subject_hash, &Self::account_id(), value)?;
+    Self::take_stake(who, subject_hash, value)?;
+
+    Self::deposit_event(RawEvent::Forfeited(who.clone(), subject_hash, value));
+    Ok(())
+  }
+
+  // Removes a stake from the stakes of an account, empty stakes are removed.
+  fn take_stake(who: &T::AccountId, subject_hash: T::Hash, value: BalanceOf<T, I>) -> Result<(), Error> {
+    let stake = Self::stakes(who, &subject_hash).checked_sub(&value).ok_or(Error::StakeNotReserved)?;
+    let staked = Self::staked_balance(who).checked_sub(&value).ok_or(Error::StakeNotReserved)?;
+
+    if stake.is_zero() {
+      <Stakes<T, I>>::remove(who, &subject_hash);
+    } else {
+      <Stakes<T, I>>::insert(who, &subject_hash, stake);
+    }
+    <StakedBalance<T, I>>::insert(who, staked);
+    Ok(())
+  }
+
+  // Get a registry, if it exists.
+  pub(crate) fn registry(
+    registry_id: RegistryId,
+  ) -> Result<Registry<T::AccountId, BalanceOf<T, I>, MomentOf<T, I>>, Error> {
+    ensure!(<Registries<T, I>>::exists(registry_id), Error::RegistryNotFound);
+    Ok(Self::registries(registry_id))
+  }
+
+  // Resolves the status of a listing.
+  // Moves it to whitelisted, or removes it from the registry if it lost a challenge.
+  // Checks if the listing is challenged or not.
+  // Further checks if apply stage or reveal stage has passed.
+  // Compares if revealed votes are in favour of whitelisting.
+  // Updates the listing status.
+  fn do_resolve(registry_id: RegistryId, listing_id: u32) -> Result<(), Error> {
+    Self::ensure_migrated()?;
+    ensure!(<ListingIndexHash<T, I>>::exists((registry_id, listing_id)), Error::ListingNotFound);
+
+    let listing_hash = Self::index_hash((registry_id, listing_id));
+    let listing = Self::listings(listing_hash);
+
+    // Check if listing is challenged.
+    let (challenge_id, was_whitelisted) = match listing.status {
+      ListingStatus::Challenged { challenge_id, whitelisted } => (challenge_id, whitelisted),
+      ListingStatus::Applied => {
+        // No challenge.
+        // Check if apply stage length has passed.
+        let now = T::Clock::now();
+        ensure!(listing.application_expiry < now, Error::ApplyStageNotPassed);
+
+        // Update listing status.
+        Self::set_status(listing_hash, &listing, ListingStatus::Whitelisted)?;
+        Self::unschedule_resolution(registry_id, listing_id);
+
+        Self::deposit_event(RawEvent::Accepted(listing_hash));
+        return Ok(());
+      },
+      _ => return Err(Error::AlreadyWhitelisted),
+    };
+
+    // Settle the stakes of the challenge.
+    // Listing owner's deposit stays reserved if the listing remains in the registry.
+    let whitelisted = Self::settle_challenge(challenge_id, listing.owner.clone(), listing.deposit)?;
+
+    // Update listing status.
+    // A rejected listing loses its stake and is removed from the registry.
+    let status = if whitelisted {
+      ListingStatus::Whitelisted
+    } else if was_whitelisted {
+      ListingStatus::Removed(challenge_id)
+    } else {
+      ListingStatus::Rejected(challenge_id)
+    };
+    Self::set_status(listing_hash, &listing, status)?;
+    Self::unschedule_resolution(registry_id, listing_id);
+
+    // Raise appropriate event as per whitelisting status.
+    if whitelisted == true {
+      Self::deposit_event(RawEvent::Accepted(listing_hash));
+    } else {
+      Self::deposit_event(RawEvent::Rejected(listing_hash));
+    }
+
+    Self::deposit_event(RawEvent::Resolved(listing_hash, challenge_id));
+    Ok(())
+  }
+
+  // Moves a listing to a new status, if the transition is valid.
+  // Listings with a final status are removed from the registry, the hash is free to be listed again.
+  // Every status is kept in the listing history.
+  fn set_status(
+    listing_hash: T::Hash,
+    listing: &Listing<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>,
+    status: ListingStatus,
+  ) -> Result<(), Error> {
+    ensure!(listing.status.can_become(&status), Error::InvalidStatusTransition);
+
+    if status.is_final() {
+      <Listings<T, I>>::remove(listing_hash);
+      <ListingIndexHash<T, I>>::remove((listing.registry_id, listing.id));
+    } else {
+      <Listings<T, I>>::mutate(listing_hash, |listing| listing.status = status);
+    }
+    Self::record_status(listing_hash, status);
+    Ok(())
+  }
+
+  // Migrates the storage of version 0, the first release of the module, to the current layout.
+  // Starts on the first block after the runtime upgrade and migrates `MaxMigrationsPerBlock` challenges and listings
+  // per block, the listings and challenges of the module cannot change until it is done, see `ensure_migrated`.
+  // The single registry of version 0 becomes the genesis registry, with id 0.
+  // Its listings are re-keyed with the registry id, their whitelisted flag and challenge id become a status,
+  // and the stakes locked for them in the currency are reserved again from the accounts still staking them.
+  // The rest of the locked stakes, votes and stakes lost in challenges, pays out the rewards from the TCR account.
+  // Admins other than the owner cannot be enumerated and have to be added again.
+  // Votes cannot be enumerated either, they are migrated when they are read, see `stored_vote`.
+  fn migrate_from_v0() {
+    let mut cursor = match Self::migration_cursor() {
+      Some(cursor) => cursor,
+      None => {
+        // Instances added after the first release have nothing to migrate.
+        if !Self::migrate_registry_from_v0() {
+          Self::endow_account();
+          <StorageVersion<I>>::put(CURRENT_STORAGE_VERSION);
+          return;
+        }
+        0
+      },
+    };
+
+    // Challenges first, they point to the listings by their old hash.
+    let challenge_count = Self::poll_nonce().saturating_sub(1);
+    let listing_count: u32 = unhashed::get(&Self::v0_value_key(b"ListingCount")).unwrap_or_default();
+    let total = challenge_count.saturating_add(listing_count);
+    let batch_end = cursor.saturating_add(T::MaxMigrationsPerBlock::get()).min(total);
+    while cursor < batch_end {
+      if cursor < challenge_count {
+        Self::migrate_challenge_from_v0(cursor + 1);
+      } else {
+        Self::migrate_listing_from_v0(cursor - challenge_count);
+      }
+      cursor += 1;
+    }
+    if cursor < total {
+      <MigrationCursor<I>>::put(cursor);
+      return;
+    }
+
+    unhashed::kill(&Self::v0_value_key(b"ListingCount"));
+    <ListingCount<I>>::insert(0, listing_count);
+    <MigrationCursor<I>>::kill();
+    Self::endow_account();
+    <StorageVersion<I>>::put(CURRENT_STORAGE_VERSION);
+  }
+
+  // Turns the single registry of version 0 into the genesis registry and starts the migration of its entries.
+  // Returns false if there is no registry of version 0.
+  fn migrate_registry_from_v0() -> bool {
+    let owner = match unhashed::take::<T::AccountId>(&Self::v0_value_key(b"Owner")) {
+      Some(owner) => owner,
+      None => return false,
+    };
+    let commit_stage_len: MomentOf<T, I> = unhashed::take(&Self::v0_value_key(b"CommitStageLen")).unwrap_or_default();
+    let apply_stage_len = unhashed::take(&Self::v0_value_key(b"ApplyStageLen")).unwrap_or_default();
+    let min_deposit = unhashed::take(&Self::v0_value_key(b"MinDeposit")).unwrap_or_default();
+    <Registries<T, I>>::insert(0, Registry {
+      owner: owner.clone(),
+      min_deposit,
+      apply_stage_len,
+      commit_stage_len,
+      // Votes used to be counted when they were cast, the reveal stage is as long as the commit stage.
+      reveal_stage_len: commit_stage_len,
+      // The losing stake went to the reward pool in full.
+      dispensation_pct: Permill::zero(),
+    });
+    <RegistryCount<I>>::put(1);
+    unhashed::kill(&Self::v0_map_key(b"Admins", &owner));
+    <Admins<T, I>>::insert((0, owner), true);
+    <MigrationCursor<I>>::put(0);
+    true
+  }
+
+  // Migrates a challenge of version 0 and its poll.
+  fn migrate_challenge_from_v0(challenge_id: u32) {
+    let key = Self::v0_map_key(b"Challenges", &challenge_id);
+    let old: ChallengeV0<T::Hash, BalanceOf<T, I>, T::AccountId, MomentOf<T, I>> = match unhashed::get(&key) {
+      Some(old) => old,
+      None => return,
+    };
+    let listing_key = Self::v0_map_key(b"Listings", &old.listing_hash);
+    let mut listing: ListingV0<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>> = match unhashed::get(&listing_key) {
+      Some(listing) => listing,
+      None => return,
+    };
+    let listing_hash = <T as system::Trait>::Hashing::hash_of(&(I::PREFIX, 0 as RegistryId, &listing.data));
+
+    // Polls keep their layout, only the listing hash changes.
+    // The listing and challenge deposits were counted as votes, they are stakes of their own now.
+    let mut poll = Self::polls(challenge_id);
+    poll.listing_hash = listing_hash;
+    poll.votes_for = poll.votes_for.saturating_sub(listing.deposit);
+    poll.votes_against = poll.votes_against.saturating_sub(old.deposit);
+
+    let (reveal_ends, reward_pool, total_tokens) = if old.resolved {
+      // The reward pool of version 0 counted the listing deposit twice and the challenge deposit as a winning vote.
+      let loser_stake = if poll.passed { old.deposit } else { listing.deposit };
+      let (reward_pool, total_tokens) = Self::v0_reward_pool(&poll, loser_stake);
+      (old.voting_ends, reward_pool, total_tokens)
+    } else {
+      // Votes committed after the upgrade get a reveal stage.
+      (old.voting_ends.saturating_add(Self::registries(0).commit_stage_len), Zero::zero(), Zero::zero())
+    };
+
+    // Resolving a challenge reset the challenge id of the listing.
+    // A rejected application gets the id of the challenge back, its listing is migrated as rejected.
+    if old.resolved && !poll.passed {
+      listing.challenge_id = challenge_id;
+      unhashed::put(&listing_key, &listing);
+    }
+    <Polls<T, I>>::insert(challenge_id, poll);
+
+    <Challenges<T, I>>::insert(challenge_id, Challenge {
+      registry_id: 0,
+      listing_hash,
+      deposit: old.deposit,
+      owner: old.owner,
+      voting_ends: old.voting_ends,
+      reveal_ends,
+      resolved: old.resolved,
+      reward_pool,
+      total_tokens,
+      claimed_tokens: Zero::zero(),
+      paid_rewards: Zero::zero(),
+    });
+  }
+
+  // Migrates a listing of version 0 and reserves its stakes again.
+  // A listing whose stakes cannot all be reserved is dropped, its stakes are returned to the accounts staking them
+  // and its challenge is closed with the votes as they were counted.
+  fn migrate_listing_from_v0(listing_id: u32) {
+    let old_hash: T::Hash = match unhashed::take(&Self::v0_map_key(b"ListingIndexHash", &listing_id)) {
+      Some(old_hash) => old_hash,
+      None => return,
+    };
+    let old: ListingV0<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>> =
+      match unhashed::take(&Self::v0_map_key(b"Listings", &old_hash)) {
+        Some(old) => old,
+        None => return,
+      };
+    let listing_hash = <T as system::Trait>::Hashing::hash_of(&(I::PREFIX, 0 as RegistryId, &old.data));
+
+    let mut status = match (old.whitelisted, old.challenge_id) {
+      (true, 0) => ListingStatus::Whitelisted,
+      (false, 0) => ListingStatus::Applied,
+      (_, challenge_id) if Self::challenges(challenge_id).resolved => ListingStatus::Rejected(challenge_id),
+      (whitelisted, challenge_id) => ListingStatus::Challenged { challenge_id, whitelisted },
+    };
+
+    // The listing deposit and an ongoing challenge deposit are still at stake.
+    let mut stakes = Vec::new();
+    if !status.is_final() {
+      stakes.push((old.owner.clone(), old.deposit));
+    }
+    if let Some(challenge_id) = status.challenge_id() {
+      let challenge = Self::challenges(challenge_id);
+      stakes.push((challenge.owner, challenge.deposit));
+    }
+    let mut reserved = Vec::new();
+    let mut dropped = false;
+    for (who, value) in stakes {
+      // What is unlocked is in the free balance of the account, it stays there if the listing is dropped.
+      let unlocked = T::LockedStakes::unlock(&old_hash, &who, value);
+      if dropped || unlocked < value {
+        dropped = true;
+        continue;
+      }
+      match Self::reserve_stake(&who, listing_hash, value) {
+        Ok(()) => reserved.push((who, value)),
+        Err(e) => {
+          print(<&'static str>::from(e));
+          dropped = true;
+        },
+      }
+    }
+    let rest = T::LockedStakes::locked(&old_hash);
+    T::LockedStakes::unlock(&old_hash, &Self::account_id(), rest);
+
+    if dropped {
+      for (who, value) in reserved {
+        if let Err(e) = Self::release_stake(&who, listing_hash, value) {
+          print(<&'static str>::from(e));
+        }
+      }
+      if let Some(challenge_id) = status.challenge_id() {
+        let mut poll = Self::polls(challenge_id);
+        poll.passed = poll.votes_for >= poll.votes_against;
+        let (reward_pool, total_tokens) = Self::v0_reward_pool(&poll, Zero::zero());
+        <Polls<T, I>>::insert(challenge_id, poll);
+        <Challenges<T, I>>::mutate(challenge_id, |challenge| {
+          challenge.resolved = true;
+          challenge.reward_pool = reward_pool;
+          challenge.total_tokens = total_tokens;
+        });
+      }
+      status = ListingStatus::Exited;
+    }
+
+    if !status.is_final() {
+      <Listings<T, I>>::insert(listing_hash, Listing {
+        id: listing_id,
+        registry_id: 0,
+        data: old.data,
+        deposit: old.deposit,
+        owner: old.owner,
+        application_expiry: old.application_expiry,
+        status,
+      });
+      <ListingIndexHash<T, I>>::insert((0, listing_id), listing_hash);
+      match status {
+        ListingStatus::Applied => Self::schedule_resolution(old.application_expiry, 0, listing_id),
+        ListingStatus::Challenged { challenge_id, .. } =>
+          Self::schedule_resolution(Self::challenges(challenge_id).reveal_ends, 0, listing_id),
+        _ => {},
+      }
+    }
+    Self::record_status(listing_hash, status);
+  }
+
+  // Reward pool and winning votes of a challenge of version 0, settled with the migrated votes.
+  // The losing stake and the losing votes make up the pool, it stays in the TCR account if nobody voted to win.
+  fn v0_reward_pool(
+    poll: &Poll<T::Hash, BalanceOf<T, I>>,
+    loser_stake: BalanceOf<T, I>,
+  ) -> (BalanceOf<T, I>, BalanceOf<T, I>) {
+    let (winning_votes, losing_votes) = if poll.passed {
+      (poll.votes_for, poll.votes_against)
+    } else {
+      (poll.votes_against, poll.votes_for)
+    };
+    if winning_votes.is_zero() {
+      (Zero::zero(), Zero::zero())
+    } else {
+      (loser_stake.saturating_add(losing_votes), winning_votes)
+    }
+  }
+
+  // Storage items of version 0 have the prefix of this instance and their own name, e.g. `Tcr Owner`.
+  // Taken from a storage item of the current layout, minus its name.
+  fn v0_prefix(name: &[u8]) -> Vec<u8> {
+    let count_key = <RegistryCount<I> as generator::StorageValue<RegistryId>>::unhashed_key();
+    let mut prefix = count_key[..count_key.len() - b"RegistryCount".len()].to_vec();
+    prefix.extend_from_slice(name);
+    prefix
+  }
+
+  // Key of a storage value of version 0.
+  fn v0_value_key(name: &[u8]) -> [u8; 16] {
+    runtime_io::twox_128(&Self::v0_prefix(name))
+  }
+
+  // Key of a storage map entry of version 0.
+  fn v0_map_key<K: Encode>(name: &[u8], key: &K) -> [u8; 32] {
+    let mut prefixed_key = Self::v0_prefix(name);
+    key.encode_to(&mut prefixed_key);
+    runtime_io::blake2_256(&prefixed_key)
+  }
+
+  // Get the vote of an account on a challenge.
+  // Votes of version 0 are decoded in their old layout, as revealed votes.
+  fn stored_vote(challenge_id: u32, voter: &T::AccountId) -> Option<Vote<T::Hash, BalanceOf<T, I>>> {
+    let raw = unhashed::get_raw(&<Votes<T, I>>::hashed_key_for((challenge_id, voter.clone())))?;
+    if let Ok(vote) = Vote::decode(&mut &raw[..]) {
+      return Some(vote);
+    }
+    let old = VoteV0::<BalanceOf<T, I>>::decode(&mut &raw[..]).ok()?;
+    Some(Vote {
+      secret_hash: Default::default(),
+      value: old.value,
+      deposit: old.deposit,
+      revealed: true,
+      claimed: old.claimed,
+    })
+  }
+
+  // Appends a status to the history of a listing hash.
+  fn record_status(listing_hash: T::Hash, status: ListingStatus) {
+    let now = T::Clock::now();
+    let index = Self::listing_history_len(listing_hash);
+    <ListingHistory<T, I>>::insert(&listing_hash, &index, (now, status));
+    <ListingHistoryLen<T, I>>::insert(listing_hash, index.saturating_add(1));
+  }
+
+  // Latest status of a listing hash, with the moment it was set.
+  fn last_status(listing_hash: T::Hash) -> Option<(MomentOf<T, I>, ListingStatus)> {
+    let len = Self::listing_history_len(listing_hash);
+    if len == 0 {
+      return None;
+    }
+    Self::listing_history_entry(&listing_hash, &(len - 1))
+  }
+
+  // Schedules a listing to be resolved automatically once a deadline has passed.
+  // Replaces the earlier schedule of the listing, if any.
+  fn schedule_resolution(deadline: MomentOf<T, I>, registry_id: RegistryId, listing_id: u32) {
+    let blocks = T::BlockNumber::saturated_from(T::Clock::blocks_until(deadline).max(1));
+    let block = <system::Module<T>>::block_number().saturating_add(blocks);
+    Self::schedule_at(block, registry_id, listing_id);
+  }
+
+  // Schedules a listing to be resolved in a block.
+  fn schedule_at(block: T::BlockNumber, registry_id: RegistryId, listing_id: u32) {
+    Self::unschedule_resolution(registry_id, listing_id);
+    <ResolutionQueue<T, I>>::mutate(block, |scheduled| scheduled.push((registry_id, listing_id)));
+    <ScheduledResolution<T, I>>::insert((registry_id, listing_id), block);
+  }
+
+  // Removes a listing from the block it is scheduled in, if any.
+  fn unschedule_resolution(registry_id: RegistryId, listing_id: u32) {
+    if let Some(block) = <ScheduledResolution<T, I>>::take((registry_id, listing_id)) {
+      let mut scheduled = Self::resolution_queue(block);
+      scheduled.retain(|entry| *entry != (registry_id, listing_id));
+      if scheduled.is_empty() {
+        <ResolutionQueue<T, I>>::remove(block);
+      } else {
+        <ResolutionQueue<T, I>>::insert(block, scheduled);
+      }
+    }
+  }
+
+  // Moment after which a listing can be resolved, its application expiry or the end of its challenge reveal stage.
+  // `None` if the listing is not waiting to be resolved.
+  fn resolution_deadline(registry_id: RegistryId, listing_id: u32) -> Option<MomentOf<T, I>> {
+    if !<ListingIndexHash<T, I>>::exists((registry_id, listing_id)) {
+      return None;
+    }
+    let listing = Self::listings(Self::index_hash((registry_id, listing_id)));
+    match listing.status {
+      ListingStatus::Applied => Some(listing.application_expiry),
+      ListingStatus::Challenged { challenge_id, .. } => Some(Self::challenges(challenge_id).reveal_ends),
+      _ => None,
+    }
+  }
+
+  // Creates a challenge and the corresponding poll, and reserves the challenge deposit.
+  // The challenged subject is a listing, or a parameter proposal in the parameterizer.
+  // Returns the id of the new challenge.
+  pub(crate) fn new_challenge(
+    registry_id: RegistryId,
+    challenger: T::AccountId,
+    subject_hash: T::Hash,
+    deposit: BalanceOf<T, I>,
+  ) -> Result<u32, Error> {
+    let registry = Self::registry(registry_id)?;
+
+    // Get current time.
+    let now = T::Clock::now();
+
+    // Use the commit and reveal stage lengths of the registry.
+    let voting_exp = now.checked_add(&registry.commit_stage_len).ok_or(Error::Overflow)?;
+    let reveal_exp = voting_exp.checked_add(&registry.reveal_stage_len).ok_or(Error::Overflow)?;
+
+    let challenge = Challenge {
+      registry_id,
+      listing_hash: subject_hash,
+      deposit,
+      owner: challenger.clone(),
+      voting_ends: voting_exp,
+      reveal_ends: reveal_exp,
+      resolved: false,
+      reward_pool: 0u32.into(),
+      total_tokens: 0u32.into(),
+      claimed_tokens: 0u32.into(),
+      paid_rewards: 0u32.into(),
+    };
+
+    // Only revealed votes are counted in the poll.
+    let poll = Poll {
+      listing_hash: subject_hash,
+      votes_for: 0u32.into(),
+      votes_against: 0u32.into(),
+      passed: false,
+    };
+
+    // Deduct the deposit for challenge.
+    Self::reserve_stake(&challenger, subject_hash, deposit)?;
+
+    // Global poll nonce.
+    // Helps keep the count of challenges and in maping votes.
+    let poll_nonce = <PollNonce<I>>::get();
+
+    // Add a new challenge and the corresponding poll in the respective collections.
+    <Challenges<T, I>>::insert(poll_nonce, challenge);
+    <Polls<T, I>>::insert(poll_nonce, poll);
+
+    // Update the poll nonce.
+    <PollNonce<I>>::put(poll_nonce + 1);
+
+    Ok(poll_nonce)
+  }
+
+  // Settles the stakes of a challenge once its reveal stage has passed.
+  // The winning party gets its deposit back plus a dispensation share of the losing stake.
+  // The rest of the losing stake, along with the deposits of the losing voters, is the reward pool.
+  // If there are no winning votes to share the reward pool, it goes to the winning party too.
+  // The stake of the challenged subject stays reserved if it wins.
+  // Returns whether the challenged subject won, ties go to the subject.
+  pub(crate) fn settle_challenge(
+    challenge_id: u32,
+    subject_owner: T::AccountId,
+    subject_stake: BalanceOf<T, I>,
+  ) -> Result<bool, Error> {
+    let challenge = Self::challenges(challenge_id);
+    let poll = Self::polls(challenge_id);
+    ensure!(challenge.resolved == false, Error::ChallengeAlreadyResolved);
+
+    // Check reveal stage length has passed.
+    let now = T::Clock::now();
+    ensure!(challenge.reveal_ends < now, Error::RevealStageNotPassed);
+
+    let passed = poll.votes_for >= poll.votes_against;
+
+    let (winner, loser, loser_stake, total_tokens, losing_votes) = if passed {
+      (subject_owner, challenge.owner.clone(), challenge.deposit, poll.votes_for, poll.votes_against)
+    } else {
+      (challenge.owner.clone(), subject_owner, subject_stake, poll.votes_against, poll.votes_for)
+    };
+
+    let dispensation = Self::registries(challenge.registry_id).dispensation_pct * loser_stake;
+    let mut reward_pool = loser_stake.checked_sub(&dispensation).ok_or(Error::Underflow)?
+      .checked_add(&losing_votes).ok_or(Error::Overflow)?;
+    let mut payout = dispensation;
+    if total_tokens.is_zero() {
+      payout = payout.checked_add(&reward_pool).ok_or(Error::Overflow)?;
+      reward_pool = Zero::zero();
+    }
+
+    // Every stake moved below is checked first, so nothing is written if one of them falls short.
+    // The payout comes out of the TCR account, which holds the losing stake once it is forfeited.
+    ensure!(Self::has_stake(&loser, challenge.listing_hash, loser_stake), Error::StakeNotReserved);
+    ensure!(passed || Self::has_stake(&winner, challenge.listing_hash, challenge.deposit), Error::StakeNotReserved);
+    let available = T::Currency::free_balance(&Self::account_id())
+      .checked_add(&loser_stake).ok_or(Error::Overflow)?;
+    ensure!(available >= payout, Error::InsufficientPot);
+
+    // The losing stake is forfeited, the dispensation is paid out of it.
+    // Revealed votes were already forfeited when they were revealed.
+    Self::forfeit(&loser, challenge.listing_hash, loser_stake)?;
+    if !passed {
+      Self::release_stake(&winner, challenge.listing_hash, challenge.deposit)?;
+    }
+    T::Currency::transfer(&Self::account_id(), &winner, payout)?;
+
+    // Mutate polls collection to update the poll instance.
+    <Polls<T, I>>::mutate(challenge_id, |poll| {
+      poll.passed = passed;
+    });
+
+    // Update challenge.
+    <Challenges<T, I>>::mutate(challenge_id, |challenge| {
+      challenge.resolved = true;
+      challenge.total_tokens = total_tokens;
+      challenge.reward_pool = reward_pool;
+    });
+
+    Ok(passed)
+  }
+
+  // Reward for a winning vote.
+  // Computed as `deposit * reward_pool / total_tokens` using a 256 bit intermediate,
+  // so the only rounding is the final division, which rounds down.
+  // The sum of rewards of all winning votes can therefore never exceed the reward pool.
+  pub fn calculate_reward(
+    deposit: BalanceOf<T, I>,
+    reward_pool: BalanceOf<T, I>,
+    total_tokens: BalanceOf<T, I>,
+  ) -> Result<BalanceOf<T, I>, Error> {
+    ensure!(deposit <= total_tokens, Error::VoteDepositExceedsTokens);
+    if total_tokens.is_zero() {
+      return Ok(Zero::zero());
+    }
+
+    let deposit: u128 = deposit.try_into().map_err(|_| Error::Overflow)?;
+    let reward_pool: u128 = reward_pool.try_into().map_err(|_| Error::Overflow)?;
+    let total_tokens: u128 = total_tokens.try_into().map_err(|_| Error::Overflow)?;
+
+    // Never more than the reward pool, so it always fits back into 128 bits.
+    let reward = U256::from(deposit) * U256::from(reward_pool) / U256::from(total_tokens);
+    BalanceOf<T, I>::try_from(reward.low_u128()).map_err(|_| Error::Overflow)
+  }
+
+  // Hash committed for a vote.
+  // Voters compute this off-chain and reveal the value and salt later.
+  // The challenge and the voter are part of the hash, so a commit cannot be copied to another challenge
+  // or by another voter and revealed once the original is revealed.
+  // The salt should be random, 256 bits leave no room to guess it.
+  pub fn commit_hash(challenge_id: u32, voter: &T::AccountId, value: bool, salt: T::Hash) -> T::Hash {
+    <T as system::Trait>::Hashing::hash_of(&(challenge_id, voter, value, salt))
+  }
+
+  // Queries used by the runtime API.
+  // Unlike the storage getters, these return `None` for missing entries instead of default values.
+
+  // Get a listing by its hash.
+  pub fn get_listing(listing_hash: T::Hash) -> Option<Listing<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>> {
+    if <Listings<T, I>>::exists(listing_hash) {
+      Some(Self::listings(listing_hash))
+    } else {
+      None
+    }
+  }
+
+  // Get the statuses a listing hash went through, oldest first.
+  pub fn listing_history(listing_hash: T::Hash) -> Vec<(MomentOf<T, I>, ListingStatus)> {
+    (0..Self::listing_history_len(listing_hash))
+      .filter_map(|index| Self::listing_history_entry(&listing_hash, &index))
+      .collect()
+  }
+
+  // Get a page of the listings of a registry, in the order they were proposed.
+  // Pages are `page_size` listing ids long, at most `MAX_LISTINGS_PAGE_SIZE`.
+  // Listings removed from the registry are skipped, so a page can have less than `page_size` listings.
+  pub fn listings_page(
+    registry_id: RegistryId,
+    page: u32,
+    page_size: u32,
+  ) -> Vec<(T::Hash, Listing<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>)> {
+    let page_size = page_size.min(MAX_LISTINGS_PAGE_SIZE);
+    let start = page.saturating_mul(page_size);
+    let end = start.saturating_add(page_size).min(Self::listing_count(registry_id));
+
+    (start..end)
+      .filter(|index| <ListingIndexHash<T, I>>::exists((registry_id, *index)))
+      .map(|index| Self::index_hash((registry_id, index)))
+      .map(|listing_hash| (listing_hash, Self::listings(listing_hash)))
+      .collect()
+  }
+
+  // Get the whitelisted listings on a page of the listings of a registry, see `listings_page`.
+  // Listings that are not whitelisted are left out, so a page can be empty before the last one.
+  pub fn whitelisted_listings(
+    registry_id: RegistryId,
+    page: u32,
+    page_size: u32,
+  ) -> Vec<(T::Hash, Listing<BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>)> {
+    Self::listings_page(registry_id, page, page_size)
+      .into_iter()
+      .filter(|(_, listing)| listing.status.is_whitelisted())
+      .collect()
+  }
+
+  // Get a challenge by its id.
+  pub fn get_challenge(challenge_id: u32) -> Option<Challenge<T::Hash, BalanceOf<T, I>, T::AccountId, MomentOf<T, I>>> {
+    if <Challenges<T, I>>::exists(challenge_id) {
+      Some(Self::challenges(challenge_id))
+    } else {
+      None
+    }
+  }
+
+  // Get the poll of a challenge.
+  pub fn get_poll(challenge_id: u32) -> Option<Poll<T::Hash, BalanceOf<T, I>>> {
+    if <Polls<T, I>>::exists(challenge_id) {
+      Some(Self::polls(challenge_id))
+    } else {
+      None
+    }
+  }
+
+  // Get the vote of an account on a challenge.
+  pub fn get_vote(challenge_id: u32, voter: T::AccountId) -> Option<Vote<T::Hash, BalanceOf<T, I>>> {
+    Self::stored_vote(challenge_id, &voter)
+  }
+
+  // Tokens an account would get from `claim_reward` for its vote on a challenge.
+  // `None` if there is nothing to claim, the challenge is not resolved, the vote is already claimed
+  // or the unclaimed rewards have been burned.
+  // Losing votes can still be claimed, but pay nothing.
+  pub fn claimable_reward(challenge_id: u32, voter: T::AccountId) -> Option<BalanceOf<T, I>> {
+    let challenge = Self::get_challenge(challenge_id)?;
+    let vote = Self::get_vote(challenge_id, voter)?;
+    if !challenge.resolved || vote.claimed {
+      return None;
+    }
+
+    if !vote.revealed {
+      Some(vote.deposit)
+    } else if Self::polls(challenge_id).passed == vote.value {
+      if challenge.claimed_tokens >= challenge.total_tokens {
+        return None;
       }
-      .build_storage()
-      .unwrap()
-      .0,
-    );
+      let reward = Self::calculate_reward(vote.deposit, challenge.reward_pool, challenge.total_tokens).ok()?;
+      reward.checked_add(&vote.deposit)
+    } else {
+      Some(Zero::zero())
+    }
+  }
+
+  // Rewards an account can claim on a page of the challenges, by challenge id.
+  // Pages are `page_size` challenge ids long, at most `MAX_REWARDS_PAGE_SIZE`, starting with challenge 1.
+  // Losing votes, which pay nothing, are left out, so a page can be empty before the last one.
+  pub fn pending_rewards(voter: T::AccountId, page: u32, page_size: u32) -> Vec<(u32, BalanceOf<T, I>)> {
+    let page_size = page_size.min(MAX_REWARDS_PAGE_SIZE);
+    let start = page.saturating_mul(page_size).saturating_add(1);
+    let end = start.saturating_add(page_size).min(<PollNonce<I>>::get());
+
+    (start..end)
+      .filter_map(|challenge_id| {
+        Self::claimable_reward(challenge_id, voter.clone())
+          .filter(|reward| !reward.is_zero())
+          .map(|reward| (challenge_id, reward))
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token;
+
+  use primitives::{Blake2Hasher, H256};
+  use runtime_io::with_externalities;
+  use sr_primitives::{
+    Perbill,
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup, OnFinalize, OnInitialize},
+  };
+  use support::{assert_noop, assert_ok, impl_outer_origin, parameter_types};
+
+  impl_outer_origin! {
+    pub enum Origin for Test {}
+  }
+
+  // For testing the module, we construct most of a mock runtime. This means
+  // first constructing a configuration type (`Test`) which `impl`s each of the
+  // configuration traits of modules we want to use.
+  #[derive(Clone, Eq, PartialEq)]
+  pub struct Test;
+  parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: u32 = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::one();
+  }
+  impl system::Trait for Test {
+    type Origin = Origin;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Call = ();
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+  }
+  impl token::Trait for Test {
+    type Event = ();
+    type TokenBalance = u64;
+  }
+  parameter_types! {
+    pub const MinimumPeriod: u64 = 1;
+  }
+  impl timestamp::Trait for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+  }
+  parameter_types! {
+    pub const ExistentialDeposit: u64 = 5;
+    pub const TransferFee: u64 = 0;
+    pub const CreationFee: u64 = 0;
+  }
+  impl balances::Trait for Test {
+    type Balance = u64;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = ();
+    type TransferPayment = ();
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type TransferFee = TransferFee;
+    type CreationFee = CreationFee;
+  }
+  impl StakeCurrency<u64, H256> for balances::Module<Test> {}
+  parameter_types! {
+    pub const MaxResolutionsPerBlock: u32 = 2;
+    pub const MaxMigrationsPerBlock: u32 = 2;
+    pub const ReapplyCooldown: u64 = 5;
+    pub const RegistryDeposit: u64 = 50;
+  }
+  impl Trait for Test {
+    type Event = ();
+    type Currency = token::Module<Test>;
+    type Clock = TimestampClock<Test>;
+    type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+    type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+    type ReapplyCooldown = ReapplyCooldown;
+    type RegistryDeposit = RegistryDeposit;
+    type LockedStakes = token::Module<Test>;
+  }
+  // A second TCR instance staking in the native currency, like in the runtime.
+  impl Trait<Instance2> for Test {
+    type Event = ();
+    type Currency = balances::Module<Test>;
+    type Clock = TimestampClock<Test>;
+    type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+    type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+    type ReapplyCooldown = ReapplyCooldown;
+    type RegistryDeposit = RegistryDeposit;
+    type LockedStakes = ();
+  }
+  // A third TCR instance measuring its stages in blocks.
+  impl Trait<Instance3> for Test {
+    type Event = ();
+    type Currency = token::Module<Test>;
+    type Clock = BlockNumberClock<Test>;
+    type MaxResolutionsPerBlock = MaxResolutionsPerBlock;
+    type MaxMigrationsPerBlock = MaxMigrationsPerBlock;
+    type ReapplyCooldown = ReapplyCooldown;
+    type RegistryDeposit = RegistryDeposit;
+    type LockedStakes = ();
+  }
+  type Tcr = Module<Test>;
+  type Tcr2 = Module<Test, Instance2>;
+  type Tcr3 = Module<Test, Instance3>;
+  type Token = token::Module<Test>;
+  type Balances = balances::Module<Test>;
+
+  // Builds the genesis config store and sets mock values.
+  fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    balances::GenesisConfig::<Test> {
+      balances: vec![(1, 100), (2, 100), (3, 100), (4, 100)],
+      vesting: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    token::GenesisConfig::<Test> {
+      name: b"Test Token".to_vec(),
+      symbol: b"TST".to_vec(),
+      decimals: 0,
+      total_supply: 1000,
+      owner: 1,
+      max_supply: None,
+      balances: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    GenesisConfig::<Test> {
+      owner: 1,
+      min_deposit: 100,
+      apply_stage_len: 10,
+      commit_stage_len: 10,
+      reveal_stage_len: 10,
+      dispensation_pct: Permill::from_percent(50),
+      poll_nonce: 1,
+      initial_listings: vec![],
+      initial_admins: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    GenesisConfig::<Test, Instance2> {
+      owner: 2,
+      min_deposit: 10,
+      apply_stage_len: 5,
+      commit_stage_len: 5,
+      reveal_stage_len: 5,
+      dispensation_pct: Permill::from_percent(50),
+      poll_nonce: 1,
+      initial_listings: vec![],
+      initial_admins: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    // Stage lengths in blocks.
+    GenesisConfig::<Test, Instance3> {
+      owner: 1,
+      min_deposit: 10,
+      apply_stage_len: 3,
+      commit_stage_len: 3,
+      reveal_stage_len: 3,
+      dispensation_pct: Permill::from_percent(50),
+      poll_nonce: 1,
+      initial_listings: vec![],
+      initial_admins: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    t.into()
+  }
+
+  // Genesis with the token supply distributed and listings seeded in the genesis registry.
+  // Built into one storage, in the same order as the runtime, so the deposits can be reserved.
+  fn new_test_ext_with_initial_listings() -> runtime_io::TestExternalities<Blake2Hasher> {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    token::GenesisConfig::<Test> {
+      name: b"Test Token".to_vec(),
+      symbol: b"TST".to_vec(),
+      decimals: 0,
+      total_supply: 1000,
+      owner: 1,
+      max_supply: None,
+      balances: vec![(1, 700), (2, 300)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    GenesisConfig::<Test> {
+      owner: 1,
+      min_deposit: 100,
+      apply_stage_len: 10,
+      commit_stage_len: 10,
+      reveal_stage_len: 10,
+      dispensation_pct: Permill::from_percent(50),
+      poll_nonce: 1,
+      initial_listings: vec![
+        (1, "ListingItem1".as_bytes().into(), 100),
+        (2, "ListingItem2".as_bytes().into(), 200),
+      ],
+      initial_admins: vec![3],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
     t.into()
   }
 
+  #[test]
+  fn should_seed_listings_and_admins_in_genesis() {
+    with_externalities(&mut new_test_ext_with_initial_listings(), || {
+      // Owner and initial admins are admins without calling init.
+      assert_eq!(Tcr::admins((0, 1)), true);
+      assert_eq!(Tcr::admins((0, 3)), true);
+      assert_eq!(Tcr::admins((0, 2)), false);
+
+      assert_eq!(Tcr::listing_count(0), 2);
+      let listing = Tcr::listings(Tcr::index_hash((0, 1)));
+      assert_eq!(listing.owner, 2);
+      assert_eq!(listing.deposit, 200);
+      assert_eq!(listing.status, ListingStatus::Whitelisted);
+      assert_eq!(Tcr::listing_history(Tcr::index_hash((0, 1))), vec![(0, ListingStatus::Whitelisted)]);
+      assert_eq!(Tcr::whitelisted_listings(0, 0, 10).len(), 2);
+      assert_eq!(Token::reserved_balance_of(1), 100);
+      assert_eq!(Token::reserved_balance_of(2), 200);
+      assert_eq!(Token::balance_of(2), 100);
+      assert_supply_conserved();
+
+      // New listings are added after the initial ones.
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem3".as_bytes().into(), 100));
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 2))).id, 2);
+      assert_noop!(
+        Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 100),
+        Error::ListingAlreadyExists
+      );
+
+      // Deposits of initial listings are returned on exit.
+      assert_ok!(Tcr::exit(Origin::signed(2), 0, 1));
+      assert_eq!(Token::balance_of(2), 300);
+    });
+  }
+
   #[test]
   fn should_fail_low_deposit() {
     with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
       assert_noop!(
-        Tcr::propose(Origin::signed(1), "ListingItem1".as_bytes().into(), 99),
-        "deposit should be more than min_deposit"
+        Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 99),
+        Error::DepositTooLow
       );
     });
   }
@@ -562,6 +2223,7 @@ mod tests {
   #[test]
   fn should_init() {
     with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
       assert_ok!(Tcr::init(Origin::signed(1)));
     });
   }
@@ -569,9 +2231,11 @@ mod tests {
   #[test]
   fn should_pass_propose() {
     with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
       assert_ok!(Tcr::init(Origin::signed(1)));
       assert_ok!(Tcr::propose(
         Origin::signed(1),
+        0,
         "ListingItem1".as_bytes().into(),
         101
       ));
@@ -581,15 +2245,17 @@ mod tests {
   #[test]
   fn should_fail_challenge_same_owner() {
     with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
       assert_ok!(Tcr::init(Origin::signed(1)));
       assert_ok!(Tcr::propose(
         Origin::signed(1),
+        0,
         "ListingItem1".as_bytes().into(),
         101
       ));
       assert_noop!(
-        Tcr::challenge(Origin::signed(1), 0, 101),
-        "You cannot challenge your own listing."
+        Tcr::challenge(Origin::signed(1), 0, 0, 101),
+        Error::OwnListing
       );
     });
   }
@@ -597,14 +2263,1192 @@ mod tests {
   #[test]
   fn should_pass_challenge() {
     with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
       assert_ok!(Tcr::init(Origin::signed(1)));
       assert_ok!(Tcr::propose(
         Origin::signed(1),
+        0,
         "ListingItem1".as_bytes().into(),
         101
       ));
       assert_ok!(Token::transfer(Origin::signed(1), 2, 200));
-      assert_ok!(Tcr::challenge(Origin::signed(2), 0, 101));
+      assert_ok!(Tcr::challenge(Origin::signed(2), 0, 0, 101));
+    });
+  }
+
+  #[test]
+  fn should_stake_full_balance() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Token::transfer(Origin::signed(1), 2, 100));
+      assert_ok!(Token::transfer(Origin::signed(1), 3, 100));
+
+      assert_ok!(Tcr::propose(Origin::signed(2), 0, "ListingItem1".as_bytes().into(), 100));
+      assert_eq!(Token::balance_of(2), 0);
+      assert_eq!(Token::reserved_balance_of(2), 100);
+
+      // Failed stakes leave no listing or challenge behind.
+      assert_noop!(
+        Tcr::propose(Origin::signed(3), 0, "ListingItem2".as_bytes().into(), 101),
+        Error::InsufficientBalance
+      );
+      assert_noop!(Tcr::challenge(Origin::signed(3), 0, 0, 101), Error::InsufficientBalance);
+      assert_noop!(Tcr::deposit(Origin::signed(2), 0, 0, 1), Error::InsufficientBalance);
+      assert_eq!(Tcr::listing_count(0), 1);
+      assert_eq!(Tcr::poll_nonce(), 1);
+
+      assert_ok!(Tcr::challenge(Origin::signed(3), 0, 0, 100));
+      assert_eq!(Token::balance_of(3), 0);
+      assert_noop!(
+        Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 1),
+        Error::InsufficientBalance
+      );
+    });
+  }
+
+  // Salt of a committed vote.
+  fn salt(n: u64) -> H256 {
+    H256::from_low_u64_be(n)
+  }
+
+  // Proposes a listing from account 1 and challenges it from account 2.
+  // Accounts 3 and 4 get tokens for voting.
+  // The challenge id is 1 as the poll nonce starts at 1.
+  fn setup_challenge() {
+    assert_ok!(Token::init(Origin::signed(1)));
+    assert_ok!(Tcr::init(Origin::signed(1)));
+    assert_ok!(Tcr::propose(
+      Origin::signed(1),
+      0,
+      "ListingItem1".as_bytes().into(),
+      101
+    ));
+    assert_ok!(Token::transfer(Origin::signed(1), 2, 200));
+    assert_ok!(Token::transfer(Origin::signed(1), 3, 200));
+    assert_ok!(Token::transfer(Origin::signed(1), 4, 200));
+    assert_ok!(Tcr::challenge(Origin::signed(2), 0, 0, 101));
+  }
+
+  #[test]
+  fn should_commit_and_reveal_vote() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(42)), 50));
+      assert_eq!(Tcr::polls(1).votes_for, 0);
+
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(42)));
+      assert_eq!(Tcr::polls(1).votes_for, 50);
+      assert_eq!(Tcr::votes((1, 3)).revealed, true);
+    });
+  }
+
+  #[test]
+  fn should_fail_double_commit() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(42)), 50));
+      assert_noop!(
+        Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(42)), 50),
+        Error::VoteAlreadyCommitted
+      );
+    });
+  }
+
+  #[test]
+  fn should_fail_reveal_in_commit_stage() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(42)), 50));
+      assert_noop!(
+        Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(42)),
+        Error::CommitStageNotPassed
+      );
     });
   }
+
+  #[test]
+  fn should_fail_reveal_with_wrong_salt() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(42)), 50));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_noop!(
+        Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(43)),
+        Error::VoteMismatch
+      );
+      assert_noop!(
+        Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(42)),
+        Error::VoteMismatch
+      );
+    });
+  }
+
+  #[test]
+  fn should_fail_reveal_of_copied_commit() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      // Account 4 commits the same hash as account 3, without knowing the value.
+      let secret_hash = Tcr::commit_hash(1, &3, true, salt(42));
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, secret_hash, 50));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, secret_hash, 50));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(42)));
+      assert_noop!(
+        Tcr::reveal_vote(Origin::signed(4), 0, 1, true, salt(42)),
+        Error::VoteMismatch
+      );
+      assert_ne!(Tcr::commit_hash(1, &3, true, salt(42)), Tcr::commit_hash(2, &3, true, salt(42)));
+    });
+  }
+
+  #[test]
+  fn should_count_only_revealed_votes() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 50));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, false, salt(2)), 100));
+
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(1)));
+
+      <timestamp::Module<Test>>::set_timestamp(15);
+      assert_noop!(Tcr::resolve(Origin::signed(1), 0, 0), Error::RevealStageNotPassed);
+
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr::polls(1).votes_against, 0);
+      assert_eq!(Tcr::polls(1).passed, true);
+    });
+  }
+
+  #[test]
+  fn should_return_unrevealed_deposit() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, false, salt(2)), 100));
+      assert_eq!(Token::balance_of(4), 100);
+
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_noop!(
+        Tcr::reveal_vote(Origin::signed(4), 0, 1, false, salt(2)),
+        Error::RevealStagePassed
+      );
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_ok!(Tcr::claim_reward(Origin::signed(4), 0, 1));
+      assert_eq!(Token::balance_of(4), 200);
+    });
+  }
+
+  // Proposes a listing from account 1 and lets it pass the apply stage.
+  fn setup_whitelisted() {
+    assert_ok!(Token::init(Origin::signed(1)));
+    assert_ok!(Tcr::init(Origin::signed(1)));
+    assert_ok!(Tcr::propose(
+      Origin::signed(1),
+      0,
+      "ListingItem1".as_bytes().into(),
+      150
+    ));
+    <timestamp::Module<Test>>::set_timestamp(11);
+    assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+  }
+
+  #[test]
+  fn should_exit_whitelisted_listing() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_whitelisted();
+      let listing_hash = Tcr::index_hash((0, 0));
+      assert_eq!(Token::balance_of(1), 850);
+
+      assert_noop!(Tcr::exit(Origin::signed(2), 0, 0), Error::NotListingOwner);
+      assert_ok!(Tcr::exit(Origin::signed(1), 0, 0));
+
+      assert_eq!(Token::balance_of(1), 1000);
+      assert_eq!(Token::reserved_balance_of(1), 0);
+      assert!(!<Listings<Test>>::exists(listing_hash));
+      assert!(!<ListingIndexHash<Test>>::exists((0, 0)));
+    });
+  }
+
+  #[test]
+  fn should_remove_whitelisted_listing_on_successful_challenge() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_whitelisted();
+      assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+      assert_ok!(Token::transfer(Origin::signed(1), 3, 300));
+      let listing_hash = Tcr::index_hash((0, 0));
+
+      // Long after the apply stage.
+      <timestamp::Module<Test>>::set_timestamp(100);
+      assert_ok!(Tcr::challenge(Origin::signed(2), 0, 0, 150));
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(110);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(121);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      // The listing is removed and its stake is forfeited.
+      assert!(!<Listings<Test>>::exists(listing_hash));
+      assert!(!<ListingIndexHash<Test>>::exists((0, 0)));
+      assert_eq!(Tcr::whitelisted_listings(0, 0, 10).len(), 0);
+      assert_eq!(Token::reserved_balance_of(1), 0);
+      assert_eq!(Token::balance_of(2), 300 + 75);
+
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+      assert_eq!(Token::balance_of(3), 300 + 75);
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_keep_whitelisted_listing_on_failed_challenge() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_whitelisted();
+      assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+      let listing_hash = Tcr::index_hash((0, 0));
+
+      <timestamp::Module<Test>>::set_timestamp(100);
+      assert_ok!(Tcr::challenge(Origin::signed(2), 0, 0, 150));
+      assert_eq!(Tcr::listings(listing_hash).status, ListingStatus::Challenged { challenge_id: 1, whitelisted: true });
+      assert!(Tcr::listings(listing_hash).status.is_whitelisted());
+      <timestamp::Module<Test>>::set_timestamp(121);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      assert_eq!(Tcr::listings(listing_hash).status, ListingStatus::Whitelisted);
+      assert_eq!(Token::reserved_balance_of(1), 150);
+      assert_eq!(Token::balance_of(2), 150);
+      assert_supply_conserved();
+
+      // Can be challenged again.
+      assert_ok!(Tcr::challenge(Origin::signed(2), 0, 0, 150));
+    });
+  }
+
+  #[test]
+  fn should_fail_exit_in_apply_stage() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Tcr::propose(
+        Origin::signed(1),
+        0,
+        "ListingItem1".as_bytes().into(),
+        101
+      ));
+      assert_noop!(Tcr::exit(Origin::signed(1), 0, 0), Error::NotWhitelisted);
+    });
+  }
+
+  #[test]
+  fn should_deposit_and_withdraw() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_whitelisted();
+
+      assert_ok!(Tcr::deposit(Origin::signed(1), 0, 0, 50));
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).deposit, 200);
+      assert_eq!(Token::balance_of(1), 800);
+
+      assert_noop!(
+        Tcr::withdraw(Origin::signed(1), 0, 0, 101),
+        Error::DepositBelowMinimum
+      );
+      assert_ok!(Tcr::withdraw(Origin::signed(1), 0, 0, 100));
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).deposit, 100);
+      assert_eq!(Token::balance_of(1), 900);
+    });
+  }
+
+  #[test]
+  fn should_fail_deposit_while_challenged() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_noop!(Tcr::deposit(Origin::signed(1), 0, 0, 50), Error::ListingChallenged);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).deposit, 101);
+    });
+  }
+
+  // Sum of free and reserved tokens of all test accounts and the TCR account.
+  // Must always be equal to the total supply.
+  fn assert_supply_conserved() {
+    let total: u64 = (1..=4).map(|who| Token::balance_of(who) + Token::reserved_balance_of(who)).sum();
+    assert_eq!(total + Token::balance_of(Tcr::account_id()), Token::total_supply());
+  }
+
+  #[test]
+  fn should_settle_failed_challenge() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 100));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, false, salt(2)), 50));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_eq!(Token::reserved_balance_of(3), 100);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(1)));
+      assert_ok!(Tcr::reveal_vote(Origin::signed(4), 0, 1, false, salt(2)));
+      // Revealed votes are at stake in the poll, no longer reserved by the voters.
+      assert_eq!(Token::reserved_balance_of(3), 0);
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_supply_conserved();
+
+      // Owner gets half of the challenger's deposit.
+      assert_eq!(Token::balance_of(1), 1000 - 101 - 600 + 50);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).status, ListingStatus::Whitelisted);
+      // Owner's deposit stays reserved, the challenger's deposit is forfeited.
+      assert_eq!(Token::reserved_balance_of(1), 101);
+      assert_eq!(Token::reserved_balance_of(2), 0);
+      // The rest of the challenger's deposit and the losing votes feed the reward pool.
+      assert_eq!(Tcr::challenges(1).reward_pool, 51 + 50);
+      assert_eq!(Tcr::challenges(1).total_tokens, 100);
+
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+      assert_ok!(Tcr::claim_reward(Origin::signed(4), 0, 1));
+      assert_eq!(Token::balance_of(4), 150);
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_settle_successful_challenge() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_supply_conserved();
+
+      // Challenger gets the deposit back plus half of the listing's stake.
+      assert_eq!(Token::balance_of(2), 200 + 50);
+      // Rejected listing is removed from the registry.
+      let listing_hash = Tcr::challenges(1).listing_hash;
+      assert!(!<Listings<Test>>::exists(listing_hash));
+      assert!(!<ListingIndexHash<Test>>::exists((0, 0)));
+      assert_eq!(Tcr::listing_history(listing_hash), vec![
+        (0, ListingStatus::Applied),
+        (0, ListingStatus::Challenged { challenge_id: 1, whitelisted: false }),
+        (21, ListingStatus::Rejected(1)),
+      ]);
+      assert_eq!(Tcr::challenges(1).reward_pool, 51);
+
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_not_settle_partially() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+
+      // The challenger wins, but part of its deposit is no longer reserved.
+      // The listing's stake would be forfeited before the challenger's deposit is returned.
+      assert_eq!(<Token as ReservableCurrency<_>>::unreserve(&2, 1), 0);
+      assert_noop!(Tcr::resolve(Origin::signed(1), 0, 0), Error::StakeNotReserved);
+      assert_eq!(Token::reserved_balance_of(1), 101);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).status, ListingStatus::Challenged { challenge_id: 1, whitelisted: false });
+
+      assert_ok!(<Token as ReservableCurrency<_>>::reserve(&2, 1));
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      // A claim the TCR account cannot cover fails without marking the vote as claimed.
+      let pot = <Token as Currency<_>>::free_balance(&Tcr::account_id());
+      let _ = <Token as Currency<_>>::make_free_balance_be(&Tcr::account_id(), pot - 1);
+      assert_noop!(Tcr::claim_reward(Origin::signed(3), 0, 1), Error::InsufficientPot);
+      assert_eq!(Tcr::claimable_reward(1, 3), Some(pot));
+    });
+  }
+
+  #[test]
+  fn should_reapply_after_cooldown() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      let listing_hash = Tcr::index_hash((0, 0));
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      <timestamp::Module<Test>>::set_timestamp(25);
+      assert_noop!(
+        Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 101),
+        Error::ReapplyCooldown
+      );
+
+      // Same hash, new listing id.
+      <timestamp::Module<Test>>::set_timestamp(26);
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 101));
+      assert_eq!(Tcr::index_hash((0, 1)), listing_hash);
+      <timestamp::Module<Test>>::set_timestamp(37);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 1));
+      assert_eq!(Tcr::listing_history(listing_hash)[2..], [
+        (21, ListingStatus::Rejected(1)),
+        (26, ListingStatus::Applied),
+        (37, ListingStatus::Whitelisted),
+      ]);
+
+      // Exits are recorded too, and need no cooldown.
+      assert_ok!(Tcr::exit(Origin::signed(1), 0, 1));
+      assert_eq!(Tcr::listing_history(listing_hash).last(), Some(&(37, ListingStatus::Exited)));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 101));
+    });
+  }
+
+  #[test]
+  fn should_give_reward_pool_to_winner_without_votes() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      // Listing wins on a tie and the owner takes the whole challenge deposit.
+      assert_eq!(Token::balance_of(1), 1000 - 101 - 600 + 101);
+      assert_eq!(Tcr::challenges(1).reward_pool, 0);
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_honour_dispensation_pct() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      <Registries<Test>>::mutate(0, |registry| registry.dispensation_pct = Permill::from_percent(20));
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+
+      // Challenger gets 20% of the listing's stake, voters share the rest.
+      assert_eq!(Token::balance_of(2), 200 + 20);
+      assert_eq!(Tcr::challenges(1).reward_pool, 81);
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_not_truncate_small_rewards() {
+    // Reward pool smaller than the winning tokens.
+    assert_eq!(Tcr::calculate_reward(30, 51, 100), Ok(15));
+    assert_eq!(Tcr::calculate_reward(70, 51, 100), Ok(35));
+    assert_eq!(Tcr::calculate_reward(100, 51, 100), Ok(51));
+    assert_eq!(Tcr::calculate_reward(0, 51, 100), Ok(0));
+    // No overflow in the intermediate product.
+    assert_eq!(Tcr::calculate_reward(u64::max_value(), u64::max_value(), u64::max_value()), Ok(u64::max_value()));
+  }
+
+  #[test]
+  fn sum_of_rewards_never_exceeds_pool() {
+    // Simple linear congruential generator, to keep the test deterministic.
+    let mut seed: u64 = 42;
+    let mut next = |max: u64| {
+      seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      (seed >> 33) % max + 1
+    };
+
+    for _ in 0..1000 {
+      let winners = next(20) as usize;
+      let deposits: Vec<u64> = (0..winners).map(|_| next(1_000_000)).collect();
+      let total_tokens: u64 = deposits.iter().sum();
+      let reward_pool = next(10_000_000);
+
+      let paid: u64 = deposits.iter()
+        .map(|d| Tcr::calculate_reward(*d, reward_pool, total_tokens).unwrap())
+        .sum();
+
+      assert!(paid <= reward_pool);
+      // Each reward is rounded down by less than one token.
+      assert!(reward_pool - paid < winners as u64);
+    }
+  }
+
+  #[test]
+  fn should_sweep_dust_to_owner() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 30));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, true, salt(2)), 70));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(1)));
+      assert_ok!(Tcr::reveal_vote(Origin::signed(4), 0, 1, true, salt(2)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr::challenges(1).reward_pool, 51);
+
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+      assert_eq!(Token::balance_of(3), 200 + 15);
+      assert_ok!(Tcr::claim_reward(Origin::signed(4), 0, 1));
+      assert_eq!(Token::balance_of(4), 200 + 35);
+
+      // Owner gets the dispensation and the single token of dust.
+      assert_eq!(Token::balance_of(1), 1000 - 101 - 600 + 50 + 1);
+      assert_eq!(Token::reserved_balance_of(1), 101);
+      assert_eq!(Token::balance_of(Tcr::account_id()), 0);
+      assert_supply_conserved();
+    });
+  }
+
+  #[test]
+  fn should_burn_unclaimed_rewards() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 30));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, true, salt(2)), 70));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(1)));
+      assert_ok!(Tcr::reveal_vote(Origin::signed(4), 0, 1, true, salt(2)));
+      assert_noop!(Tcr::burn_unclaimed(system::RawOrigin::Root.into(), 0, 1), Error::ChallengeNotResolved);
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+
+      // Only root can burn, the deposit and reward of voter 4 are burned.
+      assert!(Tcr::burn_unclaimed(Origin::signed(1), 0, 1).is_err());
+      assert_ok!(Tcr::burn_unclaimed(system::RawOrigin::Root.into(), 0, 1));
+      assert_eq!(Token::balance_of(Tcr::account_id()), 0);
+      assert_eq!(Token::total_supply(), 1000 - 70 - 36);
+      assert_supply_conserved();
+
+      assert_eq!(Tcr::claimable_reward(1, 4), None);
+      assert_noop!(
+        Tcr::claim_reward(Origin::signed(4), 0, 1),
+        Error::RewardsBurned
+      );
+      assert_noop!(Tcr::burn_unclaimed(system::RawOrigin::Root.into(), 0, 1), Error::NothingToBurn);
+    });
+  }
+
+  #[test]
+  fn should_resolve_expired_application_on_finalize() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Tcr::propose(
+        Origin::signed(1),
+        0,
+        "ListingItem1".as_bytes().into(),
+        101
+      ));
+
+      // Expiry at 10, blocks are expected every 2.
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), Some(6));
+      assert_eq!(Tcr::resolution_queue(6), vec![(0, 0)]);
+      Tcr::on_finalize(5);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).status, ListingStatus::Applied);
+
+      // Blocks came faster than expected, the listing moves to a later block.
+      <system::Module<Test>>::set_block_number(6);
+      <timestamp::Module<Test>>::set_timestamp(10);
+      Tcr::on_finalize(6);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).status, ListingStatus::Applied);
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), Some(7));
+
+      <system::Module<Test>>::set_block_number(7);
+      <timestamp::Module<Test>>::set_timestamp(12);
+      Tcr::on_finalize(7);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 0))).status, ListingStatus::Whitelisted);
+      assert!(Tcr::resolution_queue(7).is_empty());
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), None);
+      assert_noop!(Tcr::resolve(Origin::signed(1), 0, 0), Error::AlreadyWhitelisted);
+    });
+  }
+
+  #[test]
+  fn should_resolve_challenge_on_finalize() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+
+      // The application is unscheduled once the listing is challenged, the reveal stage ends at 20.
+      assert!(Tcr::resolution_queue(6).is_empty());
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), Some(11));
+
+      <system::Module<Test>>::set_block_number(11);
+      <timestamp::Module<Test>>::set_timestamp(21);
+      Tcr::on_finalize(11);
+      assert_eq!(Tcr::challenges(1).resolved, true);
+      assert!(!<ListingIndexHash<Test>>::exists((0, 0)));
+      assert!(Tcr::resolution_queue(11).is_empty());
+      assert_noop!(Tcr::resolve(Origin::signed(1), 0, 0), Error::ListingNotFound);
+    });
+  }
+
+  #[test]
+  fn should_reschedule_failed_resolution() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, false, salt(1)), 100));
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, false, salt(1)));
+      let listing_hash = Tcr::index_hash((0, 0));
+
+      // The challenger's deposit is no longer staked, the challenge cannot be settled.
+      assert_ok!(Token::unlock(2, 1, listing_hash));
+      <system::Module<Test>>::set_block_number(11);
+      <timestamp::Module<Test>>::set_timestamp(21);
+      Tcr::on_finalize(11);
+      assert_eq!(Tcr::listings(listing_hash).status, ListingStatus::Challenged { challenge_id: 1, whitelisted: false });
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), Some(12));
+      assert_eq!(Tcr::resolution_queue(12), vec![(0, 0)]);
+
+      // It is resolved once the stake is back.
+      assert_ok!(Token::lock(2, 1, listing_hash));
+      <system::Module<Test>>::set_block_number(12);
+      Tcr::on_finalize(12);
+      assert_eq!(Tcr::challenges(1).resolved, true);
+      assert!(!<ListingIndexHash<Test>>::exists((0, 0)));
+      assert_eq!(Tcr::scheduled_resolution((0, 0)), None);
+      assert!(Tcr::resolution_queue(12).is_empty());
+    });
+  }
+
+  #[test]
+  fn should_bound_resolutions_per_block() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 101));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem2".as_bytes().into(), 101));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem3".as_bytes().into(), 101));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem4".as_bytes().into(), 101));
+
+      // Listings resolved manually are unscheduled and leave room for the others.
+      <system::Module<Test>>::set_block_number(6);
+      <timestamp::Module<Test>>::set_timestamp(11);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr::resolution_queue(6), vec![(0, 1), (0, 2), (0, 3)]);
+
+      Tcr::on_finalize(6);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 2))).status, ListingStatus::Whitelisted);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 3))).status, ListingStatus::Applied);
+      assert_eq!(Tcr::resolution_queue(7), vec![(0, 3)]);
+      assert_eq!(Tcr::scheduled_resolution((0, 3)), Some(7));
+
+      <system::Module<Test>>::set_block_number(7);
+      Tcr::on_finalize(7);
+      assert_eq!(Tcr::listings(Tcr::index_hash((0, 3))).status, ListingStatus::Whitelisted);
+    });
+  }
+
+  #[test]
+  fn should_measure_stages_in_blocks() {
+    with_externalities(&mut new_test_ext(), || {
+      <system::Module<Test>>::set_block_number(5);
+      <timestamp::Module<Test>>::set_timestamp(1000);
+      assert_eq!(BlockNumberClock::<Test>::now(), 5);
+      assert_eq!(TimestampClock::<Test>::now(), 1000);
+
+      // Tcr3 measures its stages in blocks, the timestamp makes no difference.
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Token::transfer(Origin::signed(1), 2, 100));
+      assert_ok!(Tcr3::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 10));
+      let listing_hash = Tcr3::index_hash((0, 0));
+      assert_eq!(Tcr3::listings(listing_hash).application_expiry, 8);
+      assert_eq!(Tcr3::scheduled_resolution((0, 0)), Some(9));
+
+      <system::Module<Test>>::set_block_number(8);
+      <timestamp::Module<Test>>::set_timestamp(1);
+      assert_noop!(Tcr3::challenge(Origin::signed(2), 0, 0, 10), Error::ApplyStagePassed);
+
+      <system::Module<Test>>::set_block_number(7);
+      assert_ok!(Tcr3::challenge(Origin::signed(2), 0, 0, 10));
+      assert_eq!(Tcr3::challenges(1).voting_ends, 10);
+      assert_eq!(Tcr3::challenges(1).reveal_ends, 13);
+      assert_eq!(Tcr3::scheduled_resolution((0, 0)), Some(14));
+
+      <system::Module<Test>>::set_block_number(13);
+      <timestamp::Module<Test>>::set_timestamp(1_000_000);
+      assert_noop!(Tcr3::resolve(Origin::signed(1), 0, 0), Error::RevealStageNotPassed);
+
+      <system::Module<Test>>::set_block_number(14);
+      Tcr3::on_finalize(14);
+      assert_eq!(Tcr3::challenges(1).resolved, true);
+      assert_eq!(Tcr3::listings(listing_hash).status, ListingStatus::Whitelisted);
+    });
+  }
+
+  #[test]
+  fn should_create_registry() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Token::transfer(Origin::signed(1), 2, 200));
+      assert_noop!(
+        Tcr::create_registry(Origin::signed(3), 10, 5, 5, 5, Permill::from_percent(10)),
+        Error::InsufficientBalance
+      );
+      assert_ok!(Tcr::create_registry(Origin::signed(2), 10, 5, 5, 5, Permill::from_percent(10)));
+      assert_eq!(Tcr::registry_count(), 2);
+      assert_eq!(Tcr::registries(1).owner, 2);
+      assert_eq!(Tcr::admins((1, 2)), true);
+      assert_eq!(Tcr::admins((0, 2)), false);
+
+      // The registry deposit is reserved from the owner.
+      assert_eq!(Token::balance_of(2), 150);
+      assert_eq!(Token::reserved_balance_of(2), 50);
+      assert_eq!(Tcr::stakes(&2, &Tcr::registry_hash(1)), 50);
+
+      assert_noop!(
+        Tcr::add_admin(Origin::signed(1), 1, 3),
+        Error::NotAdmin
+      );
+      assert_ok!(Tcr::add_admin(Origin::signed(2), 1, 3));
+    });
+  }
+
+  #[test]
+  fn should_configure_own_registry() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Tcr::create_registry(Origin::signed(1), 10, 5, 5, 5, Permill::from_percent(10)));
+
+      assert_noop!(
+        Tcr::set_registry_config(Origin::signed(2), 1, 20, 6, 7, 8, Permill::from_percent(20)),
+        Error::NotAdmin
+      );
+      assert_noop!(
+        Tcr::set_registry_config(Origin::signed(1), 2, 20, 6, 7, 8, Permill::from_percent(20)),
+        Error::RegistryNotFound
+      );
+      // The genesis registry is governed by the parameterizer.
+      assert_noop!(
+        Tcr::set_registry_config(Origin::signed(1), 0, 20, 6, 7, 8, Permill::from_percent(20)),
+        Error::RegistryGoverned
+      );
+
+      assert_ok!(Tcr::set_registry_config(Origin::signed(1), 1, 20, 6, 7, 8, Permill::from_percent(20)));
+      let registry = Tcr::registries(1);
+      assert_eq!(registry.min_deposit, 20);
+      assert_eq!(registry.apply_stage_len, 6);
+      assert_eq!(registry.commit_stage_len, 7);
+      assert_eq!(registry.reveal_stage_len, 8);
+      assert_eq!(registry.dispensation_pct, Permill::from_percent(20));
+      assert_eq!(Tcr::registries(0).min_deposit, 100);
+
+      assert_noop!(
+        Tcr::propose(Origin::signed(1), 1, "ListingItem1".as_bytes().into(), 10),
+        Error::DepositTooLow
+      );
+    });
+  }
+
+  #[test]
+  fn should_keep_registries_isolated() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+      assert_ok!(Tcr::create_registry(Origin::signed(1), 10, 5, 5, 5, Permill::from_percent(10)));
+
+      // Min deposit of the genesis registry is 100, of the new registry 10.
+      assert_noop!(
+        Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 10),
+        Error::DepositTooLow
+      );
+      assert_ok!(Tcr::propose(Origin::signed(1), 1, "ListingItem1".as_bytes().into(), 10));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 100));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem2".as_bytes().into(), 100));
+
+      assert_eq!(Tcr::listing_count(0), 2);
+      assert_eq!(Tcr::listing_count(1), 1);
+      assert!(Tcr::index_hash((0, 0)) != Tcr::index_hash((1, 0)));
+      assert_eq!(Tcr::listings(Tcr::index_hash((1, 0))).registry_id, 1);
+
+      // Stages of the new registry are shorter.
+      <timestamp::Module<Test>>::set_timestamp(6);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 1, 0));
+      assert_noop!(Tcr::resolve(Origin::signed(1), 0, 0), Error::ApplyStageNotPassed);
+      assert_noop!(Tcr::resolve(Origin::signed(1), 2, 0), Error::ListingNotFound);
+    });
+  }
+
+  #[test]
+  fn should_keep_instances_isolated() {
+    with_externalities(&mut new_test_ext(), || {
+      assert_ok!(Token::init(Origin::signed(1)));
+      assert_ok!(Tcr::init(Origin::signed(1)));
+
+      // Each instance has its own owner and currency.
+      assert_noop!(
+        Tcr2::init(Origin::signed(1)),
+        Error::NotGenesisOwner
+      );
+      assert_ok!(Tcr2::init(Origin::signed(2)));
+      assert_eq!(Tcr2::admins((0, 2)), true);
+      assert_eq!(Tcr::admins((0, 2)), false);
+
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem1".as_bytes().into(), 100));
+      assert_ok!(Tcr2::propose(Origin::signed(2), 0, "ListingItem1".as_bytes().into(), 10));
+      assert_eq!(Tcr::listing_count(0), 1);
+      assert_eq!(Tcr2::listing_count(0), 1);
+
+      // Same data, but separate listings and reserved deposits.
+      let listing_hash = Tcr::index_hash((0, 0));
+      let listing_hash2 = Tcr2::index_hash((0, 0));
+      assert!(listing_hash != listing_hash2);
+      assert_eq!(Token::reserved_balance_of(1), 100);
+      assert_eq!(Token::reserved_balance_of(2), 0);
+      assert_eq!(Balances::reserved_balance(2), 10);
+      assert_eq!(Tcr2::stakes(&2, &listing_hash2), 10);
+      assert_eq!(Tcr::staked_balance(2), 0);
+
+      // Challenge ids are per instance.
+      assert_ok!(Tcr2::challenge(Origin::signed(3), 0, 0, 10));
+      assert_eq!(Tcr2::challenges(1).listing_hash, listing_hash2);
+      assert!(!<Challenges<Test>>::exists(1));
+      assert_eq!(Tcr::listings(listing_hash).status, ListingStatus::Applied);
+
+      // Stages of the second instance are shorter.
+      <timestamp::Module<Test>>::set_timestamp(11);
+      assert_ok!(Tcr2::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr2::listings(listing_hash2).status, ListingStatus::Whitelisted);
+      assert_eq!(Tcr::listings(listing_hash).status, ListingStatus::Applied);
+    });
+  }
+
+  #[test]
+  fn should_settle_challenge_in_native_currency() {
+    with_externalities(&mut new_test_ext(), || {
+      // The TCR account is endowed in genesis, so forfeited stakes can be moved to it.
+      assert_eq!(Balances::free_balance(Tcr2::account_id()), 5);
+
+      assert_ok!(Tcr2::propose(Origin::signed(2), 0, "ListingItem1".as_bytes().into(), 10));
+      assert_ok!(Tcr2::challenge(Origin::signed(3), 0, 0, 10));
+      assert_ok!(Tcr2::commit_vote(Origin::signed(4), 0, 1, Tcr2::commit_hash(1, &4, false, salt(1)), 20));
+      <timestamp::Module<Test>>::set_timestamp(5);
+      assert_ok!(Tcr2::reveal_vote(Origin::signed(4), 0, 1, false, salt(1)));
+      <timestamp::Module<Test>>::set_timestamp(11);
+      assert_ok!(Tcr2::resolve(Origin::signed(1), 0, 0));
+
+      // Challenger gets its deposit back and half of the listing's stake.
+      assert_eq!(Balances::free_balance(3), 100 + 5);
+      assert_eq!(Balances::reserved_balance(2), 0);
+      assert_eq!(Tcr2::staked_balance(2), 0);
+
+      assert_ok!(Tcr2::claim_reward(Origin::signed(4), 0, 1));
+      assert_eq!(Balances::free_balance(4), 100 + 5);
+      assert_eq!(Balances::free_balance(Tcr2::account_id()), 5);
+    });
+  }
+
+  #[test]
+  fn should_track_stakes_per_account() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      let listing_hash = Tcr::index_hash((0, 0));
+      assert_ok!(Tcr::commit_vote(Origin::signed(2), 0, 1, Tcr::commit_hash(1, &2, false, salt(1)), 50));
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(2)), 100));
+
+      // The challenge deposit and the vote of the challenger add up.
+      assert_eq!(Tcr::stakes(&1, &listing_hash), 101);
+      assert_eq!(Tcr::stakes(&2, &listing_hash), 151);
+      assert_eq!(Tcr::staked_balance(2), 151);
+      assert_eq!(Token::reserved_balance_of(2), 151);
+
+      // Revealed votes are forfeited to the TCR account.
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(2)));
+      assert!(!<Stakes<Test>>::exists(&3, &listing_hash));
+      assert_eq!(Tcr::staked_balance(3), 0);
+
+      // The challenger's deposit is forfeited, its unrevealed vote stays staked until it is claimed.
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr::stakes(&2, &listing_hash), 50);
+      assert_ok!(Tcr::claim_reward(Origin::signed(2), 0, 1));
+      assert_eq!(Tcr::staked_balance(2), 0);
+      assert_eq!(Tcr::stakes(&1, &listing_hash), 101);
+    });
+  }
+
+  #[test]
+  fn should_query_listings() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_whitelisted();
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem2".as_bytes().into(), 100));
+      assert_ok!(Tcr::propose(Origin::signed(1), 0, "ListingItem3".as_bytes().into(), 100));
+
+      let listing_hash = Tcr::index_hash((0, 0));
+      assert_eq!(Tcr::get_listing(listing_hash), Some(Tcr::listings(listing_hash)));
+      assert_eq!(Tcr::get_listing(H256::zero()), None);
+
+      let page: Vec<u32> = Tcr::listings_page(0, 0, 2).into_iter().map(|(_, l)| l.id).collect();
+      assert_eq!(page, vec![0, 1]);
+      let page: Vec<u32> = Tcr::listings_page(0, 1, 2).into_iter().map(|(_, l)| l.id).collect();
+      assert_eq!(page, vec![2]);
+      assert!(Tcr::listings_page(0, 2, 2).is_empty());
+      assert!(Tcr::listings_page(1, 0, 2).is_empty());
+
+      let whitelisted = Tcr::whitelisted_listings(0, 0, 2);
+      assert_eq!(whitelisted, vec![(listing_hash, Tcr::listings(listing_hash))]);
+      assert!(Tcr::whitelisted_listings(0, 1, 2).is_empty());
+
+      // Pages are at most `MAX_LISTINGS_PAGE_SIZE` listings long.
+      <ListingCount<DefaultInstance>>::insert(0, 2 * MAX_LISTINGS_PAGE_SIZE);
+      for index in 3..2 * MAX_LISTINGS_PAGE_SIZE {
+        <ListingIndexHash<Test>>::insert((0, index), listing_hash);
+      }
+      assert_eq!(Tcr::listings_page(0, 0, u32::max_value()).len(), MAX_LISTINGS_PAGE_SIZE as usize);
+      assert_eq!(Tcr::whitelisted_listings(0, 1, u32::max_value()).len(), MAX_LISTINGS_PAGE_SIZE as usize);
+    });
+  }
+
+  #[test]
+  fn should_query_claimable_reward() {
+    with_externalities(&mut new_test_ext(), || {
+      setup_challenge();
+      assert_ok!(Tcr::commit_vote(Origin::signed(3), 0, 1, Tcr::commit_hash(1, &3, true, salt(1)), 100));
+      assert_ok!(Tcr::commit_vote(Origin::signed(4), 0, 1, Tcr::commit_hash(1, &4, false, salt(2)), 50));
+      assert_eq!(Tcr::get_vote(1, 3).map(|vote| vote.deposit), Some(100));
+      assert_eq!(Tcr::get_vote(1, 2), None);
+      assert_eq!(Tcr::claimable_reward(1, 3), None);
+
+      <timestamp::Module<Test>>::set_timestamp(10);
+      assert_ok!(Tcr::reveal_vote(Origin::signed(3), 0, 1, true, salt(1)));
+      assert_ok!(Tcr::reveal_vote(Origin::signed(4), 0, 1, false, salt(2)));
+      <timestamp::Module<Test>>::set_timestamp(21);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 0));
+      assert_eq!(Tcr::get_poll(1).map(|poll| poll.passed), Some(true));
+      assert_eq!(Tcr::get_challenge(2), None);
+
+      let claimable = Tcr::claimable_reward(1, 3).unwrap();
+      assert_eq!(Tcr::claimable_reward(1, 4), Some(0));
+      assert_eq!(Tcr::pending_rewards(3, 0, 10), vec![(1, claimable)]);
+      assert_eq!(Tcr::pending_rewards(3, 0, u32::max_value()), vec![(1, claimable)]);
+      assert!(Tcr::pending_rewards(3, 1, 10).is_empty());
+      assert!(Tcr::pending_rewards(4, 0, 10).is_empty());
+      let balance = Token::balance_of(3);
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 1));
+      assert_eq!(Token::balance_of(3), balance + claimable);
+      assert_eq!(Tcr::claimable_reward(1, 3), None);
+    });
+  }
+
+  // Keys of the storage of version 0, built from the storage names of the first release.
+  fn v0_value_key(module: &str, name: &str) -> [u8; 16] {
+    runtime_io::twox_128(format!("{} {}", module, name).as_bytes())
+  }
+
+  fn v0_map_key<K: Encode>(module: &str, name: &str, key: K) -> [u8; 32] {
+    let mut prefixed_key = format!("{} {}", module, name).into_bytes();
+    key.encode_to(&mut prefixed_key);
+    runtime_io::blake2_256(&prefixed_key)
+  }
+
+  #[test]
+  fn should_migrate_from_v0() {
+    // Only the currencies are built in genesis, the TCR storage is written as the first release left it.
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    balances::GenesisConfig::<Test> {
+      balances: vec![(1, 100)],
+      vesting: vec![],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    token::GenesisConfig::<Test> {
+      name: b"Test Token".to_vec(),
+      symbol: b"TST".to_vec(),
+      decimals: 0,
+      total_supply: 1000,
+      owner: 1,
+      max_supply: None,
+      balances: vec![(1, 700), (2, 200), (3, 50), (4, 50)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    with_externalities(&mut t.into(), || {
+      unhashed::put(&v0_value_key("Tcr", "Owner"), &1u64);
+      unhashed::put(&v0_map_key("Tcr", "Admins", 1u64), &true);
+      unhashed::put(&v0_value_key("Tcr", "MinDeposit"), &100u64);
+      unhashed::put(&v0_value_key("Tcr", "ApplyStageLen"), &10u64);
+      unhashed::put(&v0_value_key("Tcr", "CommitStageLen"), &10u64);
+      unhashed::put(&v0_value_key("Tcr", "PollNonce"), &3u32);
+
+      // Listing 0 is whitelisted, listing 1 is challenged by 2 with a vote for it by 4,
+      // listing 2 was rejected by a challenge of 2 with a vote against it by 3,
+      // listing 3 is whitelisted but not all of its deposit is locked.
+      // Resolving a challenge reset the challenge id of the listing.
+      let data: Vec<Vec<u8>> = vec![
+        b"ListingItem1".to_vec(),
+        b"ListingItem2".to_vec(),
+        b"ListingItem3".to_vec(),
+        b"ListingItem4".to_vec(),
+      ];
+      let old_hashes: Vec<H256> = data.iter().map(|data| BlakeTwo256::hash(data)).collect();
+      let old_layout = [(true, 0u32), (false, 1), (false, 0), (true, 0)];
+      for (listing_id, (whitelisted, challenge_id)) in old_layout.iter().enumerate() {
+        unhashed::put(&v0_map_key("Tcr", "Listings", old_hashes[listing_id]), &ListingV0 {
+          id: listing_id as u32,
+          data: data[listing_id].clone(),
+          deposit: 100u64,
+          owner: 1u64,
+          application_expiry: 10u64,
+          whitelisted: *whitelisted,
+          challenge_id: *challenge_id,
+        });
+        unhashed::put(&v0_map_key("Tcr", "ListingIndexHash", listing_id as u32), &old_hashes[listing_id]);
+      }
+      unhashed::put(&v0_value_key("Tcr", "ListingCount"), &4u32);
+
+      // Polls counted the listing and challenge deposits as votes.
+      unhashed::put(&v0_map_key("Tcr", "Challenges", 1u32), &ChallengeV0 {
+        listing_hash: old_hashes[1],
+        deposit: 100u64,
+        owner: 2u64,
+        voting_ends: 20u64,
+        resolved: false,
+        reward_pool: 0u64,
+        total_tokens: 0u64,
+      });
+      unhashed::put(&v0_map_key("Tcr", "Polls", 1u32), &Poll {
+        listing_hash: old_hashes[1],
+        votes_for: 130u64,
+        votes_against: 100u64,
+        passed: false,
+      });
+      unhashed::put(&v0_map_key("Tcr", "Votes", (1u32, 4u64)), &VoteV0 { value: true, deposit: 30u64, claimed: false });
+      unhashed::put(&v0_map_key("Tcr", "Challenges", 2u32), &ChallengeV0 {
+        listing_hash: old_hashes[2],
+        deposit: 100u64,
+        owner: 2u64,
+        voting_ends: 5u64,
+        resolved: true,
+        reward_pool: 200u64,
+        total_tokens: 150u64,
+      });
+      unhashed::put(&v0_map_key("Tcr", "Polls", 2u32), &Poll {
+        listing_hash: old_hashes[2],
+        votes_for: 100u64,
+        votes_against: 150u64,
+        passed: false,
+      });
+      unhashed::put(&v0_map_key("Tcr", "Votes", (2u32, 3u64)), &VoteV0 { value: false, deposit: 50u64, claimed: false });
+
+      // Locked stakes were taken out of the token balances and kept per listing hash.
+      for (who, balance) in &[(1u64, 340u64), (2, 100), (3, 0), (4, 20)] {
+        unhashed::put(&v0_map_key("Token", "BalanceOf", who), balance);
+      }
+      for (listing_id, locked) in [100u64, 230, 150, 60].iter().enumerate() {
+        unhashed::put(&v0_map_key("Token", "LockedDeposits", old_hashes[listing_id]), locked);
+      }
+
+      // Two challenges or listings are migrated in each block, the listings cannot change until it is done.
+      <timestamp::Module<Test>>::set_timestamp(1);
+      Tcr::on_initialize(1);
+      assert_eq!(Tcr::storage_version(), 0);
+      assert_eq!(Tcr::migration_cursor(), Some(2));
+      assert_noop!(
+        Tcr::propose(Origin::signed(1), 0, "ListingItem5".as_bytes().into(), 100),
+        Error::StorageMigrating
+      );
+      Tcr::on_initialize(2);
+      assert_eq!(Tcr::migration_cursor(), Some(4));
+      Tcr::on_initialize(3);
+      assert_eq!(Tcr::storage_version(), CURRENT_STORAGE_VERSION);
+      assert_eq!(Tcr::migration_cursor(), None);
+
+      // The single registry becomes the genesis registry.
+      assert_eq!(Tcr::registry_count(), 1);
+      assert_eq!(Tcr::registries(0), Registry {
+        owner: 1,
+        min_deposit: 100,
+        apply_stage_len: 10,
+        commit_stage_len: 10,
+        reveal_stage_len: 10,
+        dispensation_pct: Permill::zero(),
+      });
+      assert_eq!(Tcr::admins((0, 1)), true);
+      assert!(!unhashed::exists(&v0_value_key("Tcr", "MinDeposit")));
+      assert!(!unhashed::exists(&v0_value_key("Tcr", "ListingCount")));
+
+      // Listings are re-keyed with the registry id.
+      assert_eq!(Tcr::listing_count(0), 4);
+      let hashes: Vec<H256> = data.iter().map(|data| BlakeTwo256::hash_of(&(DefaultInstance::PREFIX, 0u32, data))).collect();
+      assert_eq!(Tcr::index_hash((0, 0)), hashes[0]);
+      assert_eq!(Tcr::listings(hashes[0]).status, ListingStatus::Whitelisted);
+      assert_eq!(Tcr::listings(hashes[1]).status, ListingStatus::Challenged { challenge_id: 1, whitelisted: false });
+      assert!(!unhashed::exists(&v0_map_key("Tcr", "Listings", old_hashes[0])));
+
+      // Rejected applications are removed.
+      assert!(!<ListingIndexHash<Test>>::exists((0, 2)));
+      assert!(!<Listings<Test>>::exists(hashes[2]));
+      assert_eq!(Tcr::listing_history(hashes[2]).last(), Some(&(1, ListingStatus::Rejected(2))));
+
+      // Listings whose stake cannot be reserved again are dropped, what was locked goes back to the owner.
+      assert!(!<ListingIndexHash<Test>>::exists((0, 3)));
+      assert!(!<Listings<Test>>::exists(hashes[3]));
+      assert_eq!(Tcr::listing_history(hashes[3]).last(), Some(&(1, ListingStatus::Exited)));
+      assert_eq!(Tcr::stakes(1, &hashes[3]), 0);
+      assert_eq!(Token::balance_of(1), 340 + 60);
+
+      // Challenges get a reveal stage if they are ongoing, polls no longer count the deposits.
+      assert_eq!(Tcr::challenges(1).listing_hash, hashes[1]);
+      assert_eq!(Tcr::challenges(1).reveal_ends, 30);
+      assert_eq!(Tcr::polls(1).votes_for, 30);
+      assert_eq!(Tcr::polls(1).votes_against, 0);
+
+      // Resolved challenges pay out the losing deposit and votes to the winning votes, as counted after the migration.
+      assert_eq!(Tcr::polls(2).votes_against, 50);
+      assert_eq!(Tcr::challenges(2).reward_pool, 100);
+      assert_eq!(Tcr::challenges(2).total_tokens, 50);
+
+      // Stakes still at stake are reserved, the rest is held by the TCR account.
+      assert_eq!(Tcr::stakes(1, &hashes[0]), 100);
+      assert_eq!(Tcr::stakes(1, &hashes[1]), 100);
+      assert_eq!(Tcr::stakes(2, &hashes[1]), 100);
+      assert_eq!(Token::reserved_balance_of(1), 200);
+      assert_eq!(Token::reserved_balance_of(2), 100);
+      assert_eq!(Token::balance_of(Tcr::account_id()), 180);
+      assert_eq!(Token::locked_deposits(old_hashes[1]), 0);
+      assert_eq!(Token::total_supply(), 1000);
+
+      // Votes are read in the old layout, as revealed votes.
+      assert_eq!(Tcr::get_vote(2, 3), Some(Vote {
+        secret_hash: H256::default(),
+        value: false,
+        deposit: 50,
+        revealed: true,
+        claimed: false,
+      }));
+      assert_ok!(Tcr::claim_reward(Origin::signed(3), 0, 2));
+      assert_eq!(Token::balance_of(3), 50 + 50 * 100 / 50);
+      assert_eq!(Tcr::get_vote(2, 3).map(|vote| vote.claimed), Some(true));
+      <timestamp::Module<Test>>::set_timestamp(25);
+      assert_noop!(Tcr::reveal_vote(Origin::signed(4), 0, 1, true, salt(0)), Error::VoteAlreadyRevealed);
+
+      // The ongoing challenge settles with the stakes reserved by the migration.
+      <timestamp::Module<Test>>::set_timestamp(31);
+      assert_ok!(Tcr::resolve(Origin::signed(1), 0, 1));
+      assert_eq!(Tcr::listings(hashes[1]).status, ListingStatus::Whitelisted);
+      assert_ok!(Tcr::claim_reward(Origin::signed(4), 0, 1));
+      assert_eq!(Token::balance_of(4), 20 + 30 + 100);
+
+      // Instances added after the first release have nothing to migrate.
+      Tcr2::on_initialize(1);
+      assert_eq!(Tcr2::storage_version(), CURRENT_STORAGE_VERSION);
+      assert_eq!(Tcr2::registry_count(), 0);
+    });
+  }
+
+  #[test]
+  fn should_reject_invalid_status_transitions() {
+    let challenged = ListingStatus::Challenged { challenge_id: 1, whitelisted: false };
+    assert!(ListingStatus::Applied.can_become(&challenged));
+    assert!(challenged.can_become(&ListingStatus::Rejected(1)));
+    assert!(!challenged.can_become(&ListingStatus::Rejected(2)));
+    assert!(!challenged.can_become(&ListingStatus::Removed(1)));
+    assert!(!ListingStatus::Applied.can_become(&ListingStatus::Exited));
+    assert!(!ListingStatus::Whitelisted.can_become(&challenged));
+    assert!(!ListingStatus::Exited.can_become(&ListingStatus::Applied));
+  }
 }
@@ -1,14 +1,23 @@
 /// Runtime module implementing the token transfer functions.
-/// With added lock and unlock functions for staking in TCR runtime.
 /// Implements a custom type `TokenBalance` for representing account balance.
 /// `TokenBalance` type is similar to the `Balance` type in `balances` SRML module.
+/// Also implements the `Currency` and `ReservableCurrency` traits,
+/// so the token can be used as the currency of the TCR module.
+/// Tokens can be minted by root and burned by their holders, up to an optional max supply.
 
 use rstd::prelude::*;
-use rstd::fmt::Debug;
+use rstd::{fmt::Debug, result};
 use codec::Codec;
-use support::{dispatch::Result, Parameter, decl_storage, decl_module, decl_event, ensure};
-use system::{self, ensure_signed};
-use sr_primitives::traits::{CheckedSub, CheckedAdd, Member, SimpleArithmetic, MaybeSerializeDeserialize};
+use support::{
+    dispatch::Result, Parameter, decl_error, decl_storage, decl_module, decl_event, ensure,
+    traits::{
+        Currency, ExistenceRequirement, Imbalance, ReservableCurrency, SignedImbalance, TryDrop, WithdrawReasons,
+    },
+};
+use system::{self, ensure_root, ensure_signed};
+use sr_primitives::traits::{CheckedSub, CheckedAdd, Member, SimpleArithmetic, MaybeSerializeDeserialize, Saturating, Zero};
+use self::imbalances::{NegativeImbalance, PositiveImbalance};
+use crate::tcr;
 
 // Configuration trait for this module.
 pub trait Trait: system::Trait {
@@ -19,52 +28,116 @@ pub trait Trait: system::Trait {
 
 decl_module! {
   pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+      type Error = Error;
+
       // Initialize the default event for this module.
       fn deposit_event() = default;
 
+      // Initialize the token.
+      // Transfers the total_supply amout to the owner set in genesis config.
+      // The token becomes usable.
+      // Similar to the ERC20 smart contract constructor.
+      pub fn init(origin) -> result::Result<(), Error> {
+          let sender = ensure_signed(origin)?;
+          ensure!(sender == Self::owner(), Error::NotGenesisOwner);
+          ensure!(Self::is_init() == false, Error::AlreadyInitialized);
+
+          let total_supply = Self::total_supply();
+          <BalanceOf<T>>::insert(sender.clone(), total_supply);
+          <Init>::put(true);
+
+          Self::deposit_event(RawEvent::Initialized(sender, total_supply));
+          Ok(())
+      }
+
+      // Create new tokens in the balance of an account.
+      // Only root can mint, e.g. through sudo or governance.
+      pub fn mint(origin, to: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          ensure_root(origin)?;
+          ensure!(Self::is_init(), Error::NotInitialized);
+
+          Self::_mint(to, value)
+      }
+
+      // Destroy tokens from the balance of the sender.
+      pub fn burn(origin, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          let sender = ensure_signed(origin)?;
+          Self::_burn(sender, value)
+      }
+
       // Transfer tokens from one account to another.
-      pub fn transfer(origin, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+      pub fn transfer(origin, to: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
           let sender = ensure_signed(origin)?;
           Self::_transfer(sender, to, value)
       }
 
-      // Approve token transfer from one account to another.
-      // Once this is done, then transfer_from can be called with corresponding values.
-      pub fn approve(origin, spender: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+      // Approve a spender to transfer tokens on behalf of the sender.
+      // Sets the allowance to the given value, replacing the current allowance.
+      // Once this is done, then transfer_from can be called by the spender.
+      pub fn approve(origin, spender: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
           let sender = ensure_signed(origin)?;
           // Make sure the approver/owner owns this token.
-          ensure!(<BalanceOf<T>>::exists(&sender), "Account does not own this token");
+          ensure!(<BalanceOf<T>>::exists(&sender), Error::NotTokenHolder);
+
+          Self::_set_allowance(sender, spender, value);
+          Ok(())
+      }
+
+      // Set the allowance of a spender, only if it is still at the expected value.
+      // Unlike approve, a spender cannot use both the old and the new allowance
+      // by front-running the change with a transfer_from.
+      pub fn set_allowance(origin, spender: T::AccountId, #[compact] expected: T::TokenBalance, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          let sender = ensure_signed(origin)?;
+          ensure!(Self::allowance((sender.clone(), spender.clone())) == expected, Error::UnexpectedAllowance);
+
+          Self::_set_allowance(sender, spender, value);
+          Ok(())
+      }
+
+      // Increase the allowance of a spender.
+      pub fn increase_allowance(origin, spender: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          let sender = ensure_signed(origin)?;
 
           // Get the current value of the allowance for this sender and spender combination.
           // If doesnt exist then default 0 will be returned.
           let allowance = Self::allowance((sender.clone(), spender.clone()));
-          
-          // Add the value to the current allowance.
+
           // Using checked_add (safe math) to avoid overflow.
-          let updated_allowance = allowance.checked_add(&value).ok_or("overflow in calculating allowance")?;
+          let updated_allowance = allowance.checked_add(&value).ok_or(Error::Overflow)?;
 
-          // Insert the new allownace value of this sender and spender combination.
-          <Allowance<T>>::insert((sender.clone(), spender.clone()), updated_allowance);
-          
-          // Raise the approval event.
-          Self::deposit_event(RawEvent::Approval(sender, spender, value));
+          Self::_set_allowance(sender, spender, updated_allowance);
           Ok(())
       }
 
-      // If approved, transfer from an account to another account without needing owner's signature.
-      pub fn transfer_from(_origin, from: T::AccountId, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
-          ensure!(<Allowance<T>>::exists((from.clone(), to.clone())), "Allowance does not exist.");
-          let allowance = Self::allowance((from.clone(), to.clone()));
-          ensure!(allowance >= value, "Not enough allowance.");
+      // Decrease the allowance of a spender.
+      pub fn decrease_allowance(origin, spender: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          let sender = ensure_signed(origin)?;
+
+          let allowance = Self::allowance((sender.clone(), spender.clone()));
+          let updated_allowance = allowance.checked_sub(&value).ok_or(Error::AllowanceUnderflow)?;
+
+          Self::_set_allowance(sender, spender, updated_allowance);
+          Ok(())
+      }
+
+      // If approved, the sender (spender) transfers tokens of the owner to any account,
+      // without needing owner's signature.
+      pub fn transfer_from(origin, from: T::AccountId, to: T::AccountId, #[compact] value: T::TokenBalance) -> result::Result<(), Error> {
+          let spender = ensure_signed(origin)?;
+
+          ensure!(<Allowance<T>>::exists((from.clone(), spender.clone())), Error::AllowanceNotFound);
+          let allowance = Self::allowance((from.clone(), spender.clone()));
+          ensure!(allowance >= value, Error::AllowanceExceeded);
 
           // Using checked_sub (safe math) to avoid overflow.
-          let updated_allowance = allowance.checked_sub(&value).ok_or("overflow in calculating allowance")?;
+          let updated_allowance = allowance.checked_sub(&value).ok_or(Error::Overflow)?;
 
-          // Insert the new allownace value of this sender and spender combination.
-          <Allowance<T>>::insert((from.clone(), to.clone()), updated_allowance);
+          // Transfer first, so the allowance is only used if the transfer succeeds.
+          Self::_transfer(from.clone(), to, value)?;
 
-          Self::deposit_event(RawEvent::Approval(from.clone(), to.clone(), value));
-          Self::_transfer(from, to, value)
+          // Insert the new allowance value of this owner and spender combination.
+          Self::_set_allowance(from, spender, updated_allowance);
+          Ok(())
       }
   }
 }
@@ -72,84 +145,251 @@ decl_module! {
 // Storage for this runtime module.
 decl_storage! {
   trait Store for Module<T: Trait> as Token {
+    // Name of the token, e.g. "Substrate TCR Token".
+    Name get(name) config(): Vec<u8>;
+    // Ticker symbol of the token, e.g. "TCR".
+    Symbol get(symbol) config(): Vec<u8>;
+    // Number of decimals used to display balances.
+    // A balance of `10^decimals` is displayed as one token.
+    Decimals get(decimals) config(): u8;
+    // Account that gets the total supply when the token is initialized.
+    Owner get(owner) config(): T::AccountId;
     // Bool flag to allow init to be called only once.
     Init get(is_init): bool;
     // Total supply of the token.
     // Set in the genesis config.
     // See ../src/chain_spec.rs
+    // Kept in sync when tokens are minted or burned.
     TotalSupply get(total_supply) config(): T::TokenBalance;
+    // Upper limit of the total supply, if any.
+    MaxSupply get(max_supply): Option<T::TokenBalance>;
     // Mapping of balances to accounts.
     BalanceOf get(balance_of): map T::AccountId => T::TokenBalance;
     // Mapping of allowances to accounts.
+    // Keyed by owner and spender.
     Allowance get(allowance): map (T::AccountId, T::AccountId) => T::TokenBalance;
     // Stores the total deposit for a listing.
-    // Maps a listing hash with the total tokensface.
+    // Written by runtimes that locked the TCR stakes in the token, before they were reserved.
+    // Only read to migrate those stakes, see the TCR module.
     LockedDeposits get(locked_deposits): map T::Hash => T::TokenBalance;
+    // Tokens reserved through the `ReservableCurrency` interface.
+    // Reserved tokens are not part of the balance of the account.
+    // Includes the locked tokens of the account.
+    ReservedBalance get(reserved_balance_of): map T::AccountId => T::TokenBalance;
+    // Tokens each account has locked for each listing or proposal hash, e.g. the stakes of the TCR.
+    Locks get(locks): double_map T::AccountId, blake2_256(T::Hash) => T::TokenBalance;
+    // Total tokens locked by each account, over all hashes.
+    LockedBalance get(locked_balance_of): map T::AccountId => T::TokenBalance;
+  }
+  add_extra_genesis {
+    // Optional cap of the total supply, no cap if `None`.
+    config(max_supply): Option<T::TokenBalance>;
+    // Initial distribution of the total supply.
+    // If set, the token is initialized at genesis and `init` cannot be called.
+    // If empty, the owner gets the total supply by calling `init`.
+    config(balances): Vec<(T::AccountId, T::TokenBalance)>;
+    build(|config: &GenesisConfig<T>| {
+      if let Some(max_supply) = config.max_supply {
+        assert!(config.total_supply <= max_supply, "Total supply exceeds the max supply.");
+        <MaxSupply<T>>::put(max_supply);
+      }
+
+      if !config.balances.is_empty() {
+        let mut distributed: T::TokenBalance = Zero::zero();
+        for (who, balance) in &config.balances {
+          distributed = distributed.checked_add(balance).expect("Genesis balances overflow.");
+          <BalanceOf<T>>::mutate(who, |b| *b += *balance);
+        }
+        assert!(distributed == config.total_supply, "Genesis balances must add up to the total supply.");
+        <Init>::put(true);
+      }
+    });
   }
 }
 
 // events
 decl_event!(
-    pub enum Event<T> where AccountId = <T as system::Trait>::AccountId, TokenBalance = <T as self::Trait>::TokenBalance {
+    pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
+    TokenBalance = <T as self::Trait>::TokenBalance,
+    Hash = <T as system::Trait>::Hash {
         // Event for transfer of tokens.
         Transfer(AccountId, AccountId, TokenBalance),
-        // Event when an approval is made.
+        // Event when the allowance of a spender changes.
+        // Owner, spender and the new allowance.
         Approval(AccountId, AccountId, TokenBalance),
+        // Event when the token is initialized and the total supply is given to an account.
+        Initialized(AccountId, TokenBalance),
+        // Event when tokens of an account are locked for a listing or proposal hash.
+        Locked(AccountId, Hash, TokenBalance),
+        // Event when tokens locked for a hash are returned to the balance of the account.
+        Unlocked(AccountId, Hash, TokenBalance),
+        // Event when tokens of an account are reserved.
+        Reserved(AccountId, TokenBalance),
+        // Event when reserved tokens are returned to the balance of the account.
+        Unreserved(AccountId, TokenBalance),
+        // Event when reserved tokens of an account are moved to the balance of another account.
+        ReserveRepatriated(AccountId, AccountId, TokenBalance),
+        // Event when tokens are slashed from the balance of an account.
+        Slashed(AccountId, TokenBalance),
+        // Event when reserved tokens of an account are slashed.
+        ReserveSlashed(AccountId, TokenBalance),
+        // Event when the balance of an account is set, with the new balance.
+        BalanceSet(AccountId, TokenBalance),
+        // Event when new tokens are minted to an account.
+        Minted(AccountId, TokenBalance),
+        // Event when tokens of an account are burned or withdrawn through the `Currency` interface.
+        Burned(AccountId, TokenBalance),
+        // Event when tokens locked for a TCR listing by an older runtime are moved to the balance of an account.
+        LockReleased(AccountId, TokenBalance),
     }
 );
 
+// errors
+// Dispatch errors of the module, listed in the metadata with their index.
+decl_error! {
+    pub enum Error {
+        // Only the owner set in genesis config can initialize the token.
+        NotGenesisOwner,
+        // The token is already initialized.
+        AlreadyInitialized,
+        // The token is not initialized.
+        NotInitialized,
+        // The account does not own this token.
+        NotTokenHolder,
+        // The balance of the account is too low.
+        InsufficientBalance,
+        // The allowance does not exist.
+        AllowanceNotFound,
+        // The value is more than the allowance.
+        AllowanceExceeded,
+        // The allowance cannot go below zero.
+        AllowanceUnderflow,
+        // The allowance is not the expected value.
+        UnexpectedAllowance,
+        // The account has not locked enough tokens for the hash.
+        InsufficientLock,
+        // Minting would exceed the max supply.
+        MaxSupplyExceeded,
+        // Arithmetic overflow.
+        Overflow,
+    }
+}
+
 /// All functions in the decl_module macro become part of the public interface of the module.
 /// If they are there, they are accessible via extrinsics calls whether they are public or not.
 /// However, in the impl module section (this, below) the functions can be public and private.
 /// Private functions are internal to this module e.g.: _transfer.
-/// Public functions can be called from other modules e.g.: lock and unlock (being called from the tcr module).
+/// Public functions can be called from other modules e.g.: lock and unlock.
 /// All functions in the impl module section are not part of public interface because they are not part of the Call enum.
 impl<T: Trait> Module<T> {
-    // Initialize the token.
-    // Transfers the total_supply amout to the caller.
-    // The token becomes usable.
-    // Similar to the ERC20 smart contract constructor.
-    pub fn init(sender: T::AccountId) -> Result {
-        ensure!(Self::is_init() == false, "Token already initialized.");
-
-        <BalanceOf<T>>::insert(sender, Self::total_supply());
-        <Init>::put(true);
+    // Lock tokens of an account for a listing or proposal hash, e.g. a stake in the TCR.
+    // Locked tokens are reserved, the full balance of an account can be locked.
+    pub fn lock(from: T::AccountId, value: T::TokenBalance, listing_hash: T::Hash) -> result::Result<(), Error> {
+        // All checks are done before anything is written, a failed lock leaves no trace.
+        let (updated_balance, updated_reserved, updated_locked_balance) = Self::check_lock(&from, value)?;
+        let updated_lock = Self::locks(&from, &listing_hash).checked_add(&value).ok_or(Error::Overflow)?;
 
+        <BalanceOf<T>>::insert(&from, updated_balance);
+        <ReservedBalance<T>>::insert(&from, updated_reserved);
+        <Locks<T>>::insert(&from, &listing_hash, updated_lock);
+        <LockedBalance<T>>::insert(&from, updated_locked_balance);
+
+        Self::deposit_event(RawEvent::Locked(from, listing_hash, value));
         Ok(())
     }
 
-    // Lock user deposits for curation actions.
-    pub fn lock(from: T::AccountId, value: T::TokenBalance, listing_hash: T::Hash) -> Result {
-        ensure!(<BalanceOf<T>>::exists(from.clone()), "Account does not own this token");
+    // Whether an account can lock the given value.
+    // Callers check this before changing any state, `lock` fails only if this is false.
+    pub fn can_lock(who: &T::AccountId, value: T::TokenBalance) -> bool {
+        Self::check_lock(who, value).is_ok()
+    }
 
-        let sender_balance = Self::balance_of(from.clone());
-        ensure!(sender_balance > value, "Not enough balance.");
-        let updated_from_balance = sender_balance.checked_sub(&value).ok_or("overflow in calculating balance")?;
-        let deposit = Self::locked_deposits(listing_hash);
-        let updated_deposit = deposit.checked_add(&value).ok_or("overflow in calculating deposit")?;
+    // Checks a lock of an account, shared by `lock` and `can_lock`.
+    // Returns the balance, reserved balance and locked balance of the account after the lock.
+    // The lock of a single hash is at most the locked balance, it cannot overflow if the locked balance does not.
+    fn check_lock(
+        who: &T::AccountId,
+        value: T::TokenBalance,
+    ) -> result::Result<(T::TokenBalance, T::TokenBalance, T::TokenBalance), Error> {
+        ensure!(<BalanceOf<T>>::exists(who), Error::NotTokenHolder);
 
-        // Deduct the deposit from balance.
-        <BalanceOf<T>>::insert(from, updated_from_balance);
-        
-        // Add to deposits.
-        <LockedDeposits<T>>::insert(listing_hash, updated_deposit);
+        let balance = Self::balance_of(who);
+        ensure!(balance >= value, Error::InsufficientBalance);
+        let updated_reserved = Self::reserved_balance_of(who).checked_add(&value).ok_or(Error::Overflow)?;
+        let updated_locked_balance = Self::locked_balance_of(who).checked_add(&value).ok_or(Error::Overflow)?;
+        Ok((balance - value, updated_reserved, updated_locked_balance))
+    }
 
+    // Unlock tokens of an account when they are no longer at stake.
+    // An account can only unlock what it locked for the hash itself.
+    pub fn unlock(to: T::AccountId, value: T::TokenBalance, listing_hash: T::Hash) -> result::Result<(), Error> {
+        let lock = Self::locks(&to, &listing_hash);
+        ensure!(lock >= value, Error::InsufficientLock);
+        let updated_locked_balance = Self::locked_balance_of(&to).checked_sub(&value).ok_or(Error::InsufficientLock)?;
+        let updated_reserved = Self::reserved_balance_of(&to).checked_sub(&value).ok_or(Error::InsufficientLock)?;
+        let updated_balance = Self::balance_of(&to).checked_add(&value).ok_or(Error::Overflow)?;
+
+        if lock == value {
+            <Locks<T>>::remove(&to, &listing_hash);
+        } else {
+            <Locks<T>>::insert(&to, &listing_hash, lock - value);
+        }
+        <LockedBalance<T>>::insert(&to, updated_locked_balance);
+        <ReservedBalance<T>>::insert(&to, updated_reserved);
+        <BalanceOf<T>>::insert(&to, updated_balance);
+
+        Self::deposit_event(RawEvent::Unlocked(to, listing_hash, value));
         Ok(())
     }
 
-    // Unlock user's deposit for reward claims and challenge wins.
-    pub fn unlock(to: T::AccountId, value: T::TokenBalance, listing_hash: T::Hash) -> Result {
-        let to_balance = Self::balance_of(to.clone());
-        let updated_to_balance = to_balance.checked_add(&value).ok_or("overflow in calculating balance")?;
-        let deposit = Self::locked_deposits(listing_hash);
-        let updated_deposit = deposit.checked_sub(&value).ok_or("overflow in calculating deposit")?;
+    // Reserved tokens of an account that are not locked for any hash.
+    // Only these can be unreserved, repatriated or slashed through the `ReservableCurrency` interface.
+    fn unlocked_reserve(who: &T::AccountId) -> T::TokenBalance {
+        Self::reserved_balance_of(who).saturating_sub(Self::locked_balance_of(who))
+    }
+
+    // Internal function to update the allowance of an owner and spender combination.
+    fn _set_allowance(owner: T::AccountId, spender: T::AccountId, value: T::TokenBalance) {
+        <Allowance<T>>::insert((owner.clone(), spender.clone()), value);
 
-        // Add to user's balance.
-        <BalanceOf<T>>::insert(to, updated_to_balance);
+        // Raise the approval event.
+        Self::deposit_event(RawEvent::Approval(owner, spender, value));
+    }
+
+    // Internal function to mint tokens, respecting the max supply.
+    // Same path as depositing through the `Currency` interface, which raises the event,
+    // the total supply is increased when the positive imbalance is dropped.
+    fn _mint(to: T::AccountId, value: T::TokenBalance) -> result::Result<(), Error> {
+        let updated_supply = Self::total_supply().checked_add(&value).ok_or(Error::Overflow)?;
+        if let Some(max_supply) = Self::max_supply() {
+            ensure!(updated_supply <= max_supply, Error::MaxSupplyExceeded);
+        }
+
+        drop(<Self as Currency<_>>::deposit_creating(&to, value));
+        Ok(())
+    }
 
-        // Decrease from locked deposits.
-        <LockedDeposits<T>>::insert(listing_hash, updated_deposit);
+    // Part of `value` that can be minted without exceeding the max supply.
+    // Deposits through the `Currency` interface are capped at the max supply, like `issue`.
+    fn mintable(value: T::TokenBalance) -> T::TokenBalance {
+        match Self::max_supply() {
+            Some(max_supply) => value.min(max_supply.saturating_sub(Self::total_supply())),
+            None => value,
+        }
+    }
 
+    // Internal function to burn tokens.
+    // Same path as burning through the `Currency` interface, which raises the event,
+    // the total supply is decreased when the negative imbalance is dropped.
+    fn _burn(from: T::AccountId, value: T::TokenBalance) -> result::Result<(), Error> {
+        ensure!(Self::balance_of(from.clone()) >= value, Error::InsufficientBalance);
+        let imbalance = <Self as Currency<_>>::withdraw(
+            &from,
+            value,
+            WithdrawReasons::all(),
+            ExistenceRequirement::AllowDeath,
+        )?;
+        drop(imbalance);
         Ok(())
     }
 
@@ -158,13 +398,13 @@ impl<T: Trait> Module<T> {
         from: T::AccountId,
         to: T::AccountId,
         value: T::TokenBalance,
-    ) -> Result {
-        ensure!(<BalanceOf<T>>::exists(from.clone()), "Account does not own this token");
+    ) -> result::Result<(), Error> {
+        ensure!(<BalanceOf<T>>::exists(from.clone()), Error::NotTokenHolder);
         let sender_balance = Self::balance_of(from.clone());
-        ensure!(sender_balance >= value, "Not enough balance.");
-        let updated_from_balance = sender_balance.checked_sub(&value).ok_or("overflow in calculating balance")?;
+        ensure!(sender_balance >= value, Error::InsufficientBalance);
+        let updated_from_balance = sender_balance.checked_sub(&value).ok_or(Error::Overflow)?;
         let receiver_balance = Self::balance_of(to.clone());
-        let updated_to_balance = receiver_balance.checked_add(&value).ok_or("overflow in calculating balance")?;
+        let updated_to_balance = receiver_balance.checked_add(&value).ok_or(Error::Overflow)?;
         
         // Reduce sender's balance.
         <BalanceOf<T>>::insert(from.clone(), updated_from_balance);
@@ -176,3 +416,905 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 }
+
+// Wrappers of the token balance, to keep the total supply in sync
+// when balances are created or destroyed through the `Currency` interface.
+mod imbalances {
+    use super::{result, Imbalance, Saturating, Trait, TryDrop, Zero, TotalSupply};
+    use support::StorageValue;
+    use rstd::mem;
+
+    // Funds added to an account. Increases the total supply when dropped.
+    #[must_use]
+    pub struct PositiveImbalance<T: Trait>(T::TokenBalance);
+
+    impl<T: Trait> PositiveImbalance<T> {
+        pub fn new(amount: T::TokenBalance) -> Self {
+            PositiveImbalance(amount)
+        }
+    }
+
+    // Funds removed from an account. Decreases the total supply when dropped.
+    #[must_use]
+    pub struct NegativeImbalance<T: Trait>(T::TokenBalance);
+
+    impl<T: Trait> NegativeImbalance<T> {
+        pub fn new(amount: T::TokenBalance) -> Self {
+            NegativeImbalance(amount)
+        }
+    }
+
+    impl<T: Trait> TryDrop for PositiveImbalance<T> {
+        fn try_drop(self) -> result::Result<(), Self> {
+            self.drop_zero()
+        }
+    }
+
+    impl<T: Trait> Imbalance<T::TokenBalance> for PositiveImbalance<T> {
+        type Opposite = NegativeImbalance<T>;
+
+        fn zero() -> Self {
+            Self(Zero::zero())
+        }
+        fn drop_zero(self) -> result::Result<(), Self> {
+            if self.0.is_zero() {
+                Ok(())
+            } else {
+                Err(self)
+            }
+        }
+        fn split(self, amount: T::TokenBalance) -> (Self, Self) {
+            let first = self.0.min(amount);
+            let second = self.0 - first;
+
+            mem::forget(self);
+            (Self(first), Self(second))
+        }
+        fn merge(mut self, other: Self) -> Self {
+            self.0 = self.0.saturating_add(other.0);
+            mem::forget(other);
+
+            self
+        }
+        fn subsume(&mut self, other: Self) {
+            self.0 = self.0.saturating_add(other.0);
+            mem::forget(other);
+        }
+        fn offset(self, other: Self::Opposite) -> result::Result<Self, Self::Opposite> {
+            let (a, b) = (self.0, other.0);
+            mem::forget((self, other));
+
+            if a >= b {
+                Ok(Self(a - b))
+            } else {
+                Err(NegativeImbalance::new(b - a))
+            }
+        }
+        fn peek(&self) -> T::TokenBalance {
+            self.0
+        }
+    }
+
+    impl<T: Trait> TryDrop for NegativeImbalance<T> {
+        fn try_drop(self) -> result::Result<(), Self> {
+            self.drop_zero()
+        }
+    }
+
+    impl<T: Trait> Imbalance<T::TokenBalance> for NegativeImbalance<T> {
+        type Opposite = PositiveImbalance<T>;
+
+        fn zero() -> Self {
+            Self(Zero::zero())
+        }
+        fn drop_zero(self) -> result::Result<(), Self> {
+            if self.0.is_zero() {
+                Ok(())
+            } else {
+                Err(self)
+            }
+        }
+        fn split(self, amount: T::TokenBalance) -> (Self, Self) {
+            let first = self.0.min(amount);
+            let second = self.0 - first;
+
+            mem::forget(self);
+            (Self(first), Self(second))
+        }
+        fn merge(mut self, other: Self) -> Self {
+            self.0 = self.0.saturating_add(other.0);
+            mem::forget(other);
+
+            self
+        }
+        fn subsume(&mut self, other: Self) {
+            self.0 = self.0.saturating_add(other.0);
+            mem::forget(other);
+        }
+        fn offset(self, other: Self::Opposite) -> result::Result<Self, Self::Opposite> {
+            let (a, b) = (self.0, other.0);
+            mem::forget((self, other));
+
+            if a >= b {
+                Ok(Self(a - b))
+            } else {
+                Err(PositiveImbalance::new(b - a))
+            }
+        }
+        fn peek(&self) -> T::TokenBalance {
+            self.0
+        }
+    }
+
+    impl<T: Trait> Drop for PositiveImbalance<T> {
+        fn drop(&mut self) {
+            <TotalSupply<T>>::mutate(|v| *v = v.saturating_add(self.0));
+        }
+    }
+
+    impl<T: Trait> Drop for NegativeImbalance<T> {
+        fn drop(&mut self) {
+            <TotalSupply<T>>::mutate(|v| *v = v.saturating_sub(self.0));
+        }
+    }
+}
+
+// Stakes the TCR module locked in the token before they were reserved, see `LockedDeposits`.
+// Locked tokens are still part of the total supply, releasing them leaves it as it is.
+impl<T: Trait> tcr::LockedStakes<T::AccountId, T::Hash, T::TokenBalance> for Module<T> {
+    fn locked(listing_hash: &T::Hash) -> T::TokenBalance {
+        Self::locked_deposits(listing_hash)
+    }
+
+    fn unlock(listing_hash: &T::Hash, who: &T::AccountId, value: T::TokenBalance) -> T::TokenBalance {
+        let locked = Self::locked_deposits(listing_hash);
+        let unlocked = value.min(locked);
+        if unlocked.is_zero() {
+            return unlocked;
+        }
+
+        if unlocked == locked {
+            <LockedDeposits<T>>::remove(listing_hash);
+        } else {
+            <LockedDeposits<T>>::insert(listing_hash, locked - unlocked);
+        }
+        <BalanceOf<T>>::mutate(who, |balance| *balance = balance.saturating_add(unlocked));
+
+        Self::deposit_event(RawEvent::LockReleased(who.clone(), unlocked));
+        unlocked
+    }
+}
+
+// TCR stakes are locked in the token per listing or proposal hash.
+// Forfeited stakes are unlocked and transferred to the beneficiary.
+impl<T: Trait> tcr::StakeCurrency<T::AccountId, T::Hash> for Module<T> {
+    fn can_lock(who: &T::AccountId, value: T::TokenBalance) -> bool {
+        Self::can_lock(who, value)
+    }
+
+    fn lock(who: &T::AccountId, subject_hash: &T::Hash, value: T::TokenBalance) -> Result {
+        Self::lock(who.clone(), value, *subject_hash).map_err(Into::into)
+    }
+
+    fn unlock(who: &T::AccountId, subject_hash: &T::Hash, value: T::TokenBalance) -> Result {
+        Self::unlock(who.clone(), value, *subject_hash).map_err(Into::into)
+    }
+
+    fn repatriate_locked(
+        who: &T::AccountId,
+        subject_hash: &T::Hash,
+        beneficiary: &T::AccountId,
+        value: T::TokenBalance,
+    ) -> Result {
+        Self::unlock(who.clone(), value, *subject_hash)?;
+        Self::_transfer(who.clone(), beneficiary.clone(), value).map_err(Into::into)
+    }
+}
+
+// The token as a currency, e.g. for the stakes in the TCR module.
+// The token has no existential deposit and no locks on the balance of an account.
+// Every change to a balance raises an event, so balances can be rebuilt from the events alone.
+impl<T: Trait> Currency<T::AccountId> for Module<T> {
+    type Balance = T::TokenBalance;
+    type PositiveImbalance = PositiveImbalance<T>;
+    type NegativeImbalance = NegativeImbalance<T>;
+
+    fn total_balance(who: &T::AccountId) -> Self::Balance {
+        Self::balance_of(who).saturating_add(Self::reserved_balance_of(who))
+    }
+
+    fn can_slash(who: &T::AccountId, value: Self::Balance) -> bool {
+        Self::balance_of(who) >= value
+    }
+
+    fn total_issuance() -> Self::Balance {
+        Self::total_supply()
+    }
+
+    fn minimum_balance() -> Self::Balance {
+        Zero::zero()
+    }
+
+    fn burn(mut amount: Self::Balance) -> Self::PositiveImbalance {
+        <TotalSupply<T>>::mutate(|supply| {
+            amount = amount.min(*supply);
+            *supply -= amount;
+        });
+        PositiveImbalance::new(amount)
+    }
+
+    // Issuance is capped at the max supply.
+    fn issue(mut amount: Self::Balance) -> Self::NegativeImbalance {
+        let max_supply = Self::max_supply();
+        <TotalSupply<T>>::mutate(|supply| {
+            let mut updated_supply = supply.saturating_add(amount);
+            if let Some(max_supply) = max_supply {
+                updated_supply = updated_supply.min(max_supply.max(*supply));
+            }
+            amount = updated_supply - *supply;
+            *supply = updated_supply;
+        });
+        NegativeImbalance::new(amount)
+    }
+
+    fn free_balance(who: &T::AccountId) -> Self::Balance {
+        Self::balance_of(who)
+    }
+
+    fn ensure_can_withdraw(
+        who: &T::AccountId,
+        amount: Self::Balance,
+        _reasons: WithdrawReasons,
+        _new_balance: Self::Balance,
+    ) -> Result {
+        ensure!(Self::balance_of(who) >= amount, "Not enough balance.");
+        Ok(())
+    }
+
+    fn transfer(source: &T::AccountId, dest: &T::AccountId, value: Self::Balance) -> Result {
+        Self::_transfer(source.clone(), dest.clone(), value).map_err(Into::into)
+    }
+
+    fn slash(who: &T::AccountId, value: Self::Balance) -> (Self::NegativeImbalance, Self::Balance) {
+        let free_balance = Self::balance_of(who);
+        let slashed = free_balance.min(value);
+        <BalanceOf<T>>::insert(who, free_balance - slashed);
+
+        Self::deposit_event(RawEvent::Slashed(who.clone(), slashed));
+        (NegativeImbalance::new(slashed), value - slashed)
+    }
+
+    fn deposit_into_existing(
+        who: &T::AccountId,
+        value: Self::Balance,
+    ) -> result::Result<Self::PositiveImbalance, &'static str> {
+        ensure!(<BalanceOf<T>>::exists(who), "Account does not own this token");
+        ensure!(Self::mintable(value) == value, Error::MaxSupplyExceeded);
+        let updated_balance = Self::balance_of(who).checked_add(&value).ok_or("overflow in calculating balance")?;
+        <BalanceOf<T>>::insert(who, updated_balance);
+
+        Self::deposit_event(RawEvent::Minted(who.clone(), value));
+        Ok(PositiveImbalance::new(value))
+    }
+
+    fn deposit_creating(who: &T::AccountId, value: Self::Balance) -> Self::PositiveImbalance {
+        let balance = Self::balance_of(who);
+        let updated_balance = balance.saturating_add(Self::mintable(value));
+        <BalanceOf<T>>::insert(who, updated_balance);
+
+        Self::deposit_event(RawEvent::Minted(who.clone(), updated_balance - balance));
+        PositiveImbalance::new(updated_balance - balance)
+    }
+
+    fn withdraw(
+        who: &T::AccountId,
+        value: Self::Balance,
+        _reasons: WithdrawReasons,
+        _liveness: ExistenceRequirement,
+    ) -> result::Result<Self::NegativeImbalance, &'static str> {
+        let updated_balance = Self::balance_of(who).checked_sub(&value).ok_or("Not enough balance.")?;
+        <BalanceOf<T>>::insert(who, updated_balance);
+
+        Self::deposit_event(RawEvent::Burned(who.clone(), value));
+        Ok(NegativeImbalance::new(value))
+    }
+
+    fn make_free_balance_be(
+        who: &T::AccountId,
+        balance: Self::Balance,
+    ) -> SignedImbalance<Self::Balance, Self::PositiveImbalance> {
+        let original = Self::balance_of(who);
+        let imbalance = if original <= balance {
+            SignedImbalance::Positive(PositiveImbalance::new(balance - original))
+        } else {
+            SignedImbalance::Negative(NegativeImbalance::new(original - balance))
+        };
+        <BalanceOf<T>>::insert(who, balance);
+
+        Self::deposit_event(RawEvent::BalanceSet(who.clone(), balance));
+        imbalance
+    }
+}
+
+// Tokens locked through the `ReservableCurrency` interface are only reserved,
+// so the lock of one hash cannot be unreserved, repatriated or slashed by the others.
+impl<T: Trait> ReservableCurrency<T::AccountId> for Module<T> {
+    fn can_reserve(who: &T::AccountId, value: Self::Balance) -> bool {
+        Self::balance_of(who) >= value
+    }
+
+    fn slash_reserved(who: &T::AccountId, value: Self::Balance) -> (Self::NegativeImbalance, Self::Balance) {
+        let reserved = Self::reserved_balance_of(who);
+        let slashed = Self::unlocked_reserve(who).min(value);
+        <ReservedBalance<T>>::insert(who, reserved - slashed);
+
+        Self::deposit_event(RawEvent::ReserveSlashed(who.clone(), slashed));
+        (NegativeImbalance::new(slashed), value - slashed)
+    }
+
+    fn reserved_balance(who: &T::AccountId) -> Self::Balance {
+        Self::reserved_balance_of(who)
+    }
+
+    fn reserve(who: &T::AccountId, value: Self::Balance) -> Result {
+        ensure!(Self::can_reserve(who, value), "Not enough balance.");
+        let balance = Self::balance_of(who);
+        let updated_reserved = Self::reserved_balance_of(who).checked_add(&value).ok_or("overflow in calculating reserve")?;
+
+        <BalanceOf<T>>::insert(who, balance - value);
+        <ReservedBalance<T>>::insert(who, updated_reserved);
+
+        Self::deposit_event(RawEvent::Reserved(who.clone(), value));
+        Ok(())
+    }
+
+    fn unreserve(who: &T::AccountId, value: Self::Balance) -> Self::Balance {
+        let reserved = Self::reserved_balance_of(who);
+        let actual = Self::unlocked_reserve(who).min(value);
+
+        <ReservedBalance<T>>::insert(who, reserved - actual);
+        <BalanceOf<T>>::mutate(who, |balance| *balance = balance.saturating_add(actual));
+
+        Self::deposit_event(RawEvent::Unreserved(who.clone(), actual));
+        value - actual
+    }
+
+    fn repatriate_reserved(
+        slashed: &T::AccountId,
+        beneficiary: &T::AccountId,
+        value: Self::Balance,
+    ) -> result::Result<Self::Balance, &'static str> {
+        let reserved = Self::reserved_balance_of(slashed);
+        let actual = Self::unlocked_reserve(slashed).min(value);
+        let updated_balance = Self::balance_of(beneficiary).checked_add(&actual).ok_or("overflow in calculating balance")?;
+
+        <ReservedBalance<T>>::insert(slashed, reserved - actual);
+        <BalanceOf<T>>::insert(beneficiary, updated_balance);
+
+        Self::deposit_event(RawEvent::ReserveRepatriated(slashed.clone(), beneficiary.clone(), actual));
+        Ok(value - actual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use primitives::{Blake2Hasher, H256};
+    use runtime_io::with_externalities;
+    use sr_primitives::{
+        Perbill,
+        testing::Header,
+        traits::{BlakeTwo256, IdentityLookup},
+    };
+    use std::collections::BTreeMap;
+    use support::{assert_noop, assert_ok, impl_outer_event, impl_outer_origin, parameter_types};
+
+    impl_outer_origin! {
+        pub enum Origin for Test {}
+    }
+
+    mod token {
+        pub use super::super::Event;
+    }
+
+    impl_outer_event! {
+        pub enum TestEvent for Test {
+            token<T>,
+        }
+    }
+
+    // For testing the module, we construct most of a mock runtime. This means
+    // first constructing a configuration type (`Test`) which `impl`s each of the
+    // configuration traits of modules we want to use.
+    #[derive(Clone, Eq, PartialEq)]
+    pub struct Test;
+    parameter_types! {
+        pub const BlockHashCount: u64 = 250;
+        pub const MaximumBlockWeight: u32 = 1024;
+        pub const MaximumBlockLength: u32 = 2 * 1024;
+        pub const AvailableBlockRatio: Perbill = Perbill::one();
+    }
+    impl system::Trait for Test {
+        type Origin = Origin;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Call = ();
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<Self::AccountId>;
+        type Header = Header;
+        type Event = TestEvent;
+        type BlockHashCount = BlockHashCount;
+        type MaximumBlockWeight = MaximumBlockWeight;
+        type MaximumBlockLength = MaximumBlockLength;
+        type AvailableBlockRatio = AvailableBlockRatio;
+        type Version = ();
+    }
+    impl Trait for Test {
+        type Event = TestEvent;
+        type TokenBalance = u64;
+    }
+    type Token = Module<Test>;
+
+    // Builds the genesis config store and sets mock values.
+    fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+        new_test_ext_with_balances(vec![])
+    }
+
+    // Same mock values, with the total supply distributed in genesis.
+    fn new_test_ext_with_balances(balances: Vec<(u64, u64)>) -> runtime_io::TestExternalities<Blake2Hasher> {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            name: b"Test Token".to_vec(),
+            symbol: b"TST".to_vec(),
+            decimals: 2,
+            total_supply: 1000,
+            owner: 1,
+            max_supply: Some(1500),
+            balances,
+        }
+        .assimilate_storage(&mut t)
+        .unwrap();
+        t.into()
+    }
+
+    // Balances, reserved balances and locks rebuilt from the token events alone.
+    #[derive(Default)]
+    struct ShadowLedger {
+        balances: BTreeMap<u64, u64>,
+        reserved: BTreeMap<u64, u64>,
+        locks: BTreeMap<(u64, H256), u64>,
+    }
+
+    impl ShadowLedger {
+        fn replay() -> Self {
+            let mut ledger = ShadowLedger::default();
+            for record in <system::Module<Test>>::events() {
+                if let TestEvent::token(event) = record.event {
+                    ledger.apply(event);
+                }
+            }
+            ledger
+        }
+
+        fn apply(&mut self, event: Event<Test>) {
+            match event {
+                RawEvent::Initialized(who, value) => *self.balances.entry(who).or_default() += value,
+                RawEvent::Transfer(from, to, value) => {
+                    *self.balances.entry(from).or_default() -= value;
+                    *self.balances.entry(to).or_default() += value;
+                },
+                RawEvent::Reserved(who, value) => {
+                    *self.balances.entry(who).or_default() -= value;
+                    *self.reserved.entry(who).or_default() += value;
+                },
+                RawEvent::Unreserved(who, value) => {
+                    *self.reserved.entry(who).or_default() -= value;
+                    *self.balances.entry(who).or_default() += value;
+                },
+                RawEvent::Locked(who, hash, value) => {
+                    *self.balances.entry(who).or_default() -= value;
+                    *self.reserved.entry(who).or_default() += value;
+                    *self.locks.entry((who, hash)).or_default() += value;
+                },
+                RawEvent::Unlocked(who, hash, value) => {
+                    *self.locks.entry((who, hash)).or_default() -= value;
+                    *self.reserved.entry(who).or_default() -= value;
+                    *self.balances.entry(who).or_default() += value;
+                },
+                RawEvent::ReserveRepatriated(from, to, value) => {
+                    *self.reserved.entry(from).or_default() -= value;
+                    *self.balances.entry(to).or_default() += value;
+                },
+                RawEvent::Slashed(who, value) => *self.balances.entry(who).or_default() -= value,
+                RawEvent::ReserveSlashed(who, value) => *self.reserved.entry(who).or_default() -= value,
+                RawEvent::BalanceSet(who, balance) => *self.balances.entry(who).or_default() = balance,
+                RawEvent::Minted(who, value) => *self.balances.entry(who).or_default() += value,
+                RawEvent::Burned(who, value) => *self.balances.entry(who).or_default() -= value,
+                RawEvent::LockReleased(who, value) => *self.balances.entry(who).or_default() += value,
+                RawEvent::Approval(..) => {},
+            }
+        }
+
+        // Every account seen in the events must match storage.
+        fn assert_matches_storage(&self) {
+            for (who, balance) in &self.balances {
+                assert_eq!(Token::balance_of(who), *balance);
+            }
+            for (who, reserved) in &self.reserved {
+                assert_eq!(Token::reserved_balance_of(who), *reserved);
+            }
+            let mut locked_balances = BTreeMap::<u64, u64>::new();
+            for ((who, hash), locked) in &self.locks {
+                assert_eq!(Token::locks(who, hash), *locked);
+                *locked_balances.entry(*who).or_default() += locked;
+            }
+            for (who, locked) in locked_balances {
+                assert_eq!(Token::locked_balance_of(who), locked);
+            }
+        }
+    }
+
+    #[test]
+    fn should_set_metadata_in_genesis() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_eq!(Token::name(), b"Test Token".to_vec());
+            assert_eq!(Token::symbol(), b"TST".to_vec());
+            assert_eq!(Token::decimals(), 2);
+        });
+    }
+
+    #[test]
+    fn should_distribute_balances_in_genesis() {
+        with_externalities(&mut new_test_ext_with_balances(vec![(1, 600), (2, 300), (2, 100)]), || {
+            assert_eq!(Token::balance_of(1), 600);
+            assert_eq!(Token::balance_of(2), 400);
+            assert!(Token::is_init());
+            assert_noop!(Token::init(Origin::signed(1)), Error::AlreadyInitialized);
+            assert_ok!(Token::transfer(Origin::signed(2), 3, 400));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Genesis balances must add up to the total supply.")]
+    fn should_fail_genesis_balances_not_adding_up() {
+        new_test_ext_with_balances(vec![(1, 600), (2, 300)]);
+    }
+
+    #[test]
+    fn should_emit_init_event() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            let events: Vec<TestEvent> = <system::Module<Test>>::events().into_iter().map(|r| r.event).collect();
+            assert_eq!(events, vec![TestEvent::token(RawEvent::Initialized(1, 1000))]);
+        });
+    }
+
+    #[test]
+    fn should_replay_events_into_shadow_ledger() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+            assert_ok!(<Token as ReservableCurrency<_>>::reserve(&1, 150));
+            assert_ok!(<Token as ReservableCurrency<_>>::reserve(&2, 100));
+            assert_eq!(<Token as ReservableCurrency<_>>::unreserve(&1, 120), 0);
+
+            // Locks carry the hash they are for.
+            let listing = H256::from_low_u64_be(1);
+            assert_ok!(Token::lock(1, 100, listing));
+            assert_ok!(Token::lock(2, 40, listing));
+            assert_ok!(Token::unlock(1, 30, listing));
+            let events: Vec<TestEvent> = <system::Module<Test>>::events().into_iter().map(|r| r.event).collect();
+            assert!(events.ends_with(&[
+                TestEvent::token(RawEvent::Locked(1, listing, 100)),
+                TestEvent::token(RawEvent::Locked(2, listing, 40)),
+                TestEvent::token(RawEvent::Unlocked(1, listing, 30)),
+            ]));
+            assert_ok!(Token::approve(Origin::signed(1), 3, 200));
+            assert_ok!(Token::transfer_from(Origin::signed(3), 1, 3, 200));
+            assert_eq!(<Token as ReservableCurrency<_>>::repatriate_reserved(&2, &4, 50), Ok(0));
+
+            // Every balance change through the `Currency` interface is an event too.
+            let (slashed, _) = <Token as ReservableCurrency<_>>::slash_reserved(&2, 30);
+            let (burned, _) = <Token as Currency<_>>::slash(&3, 20);
+            assert!(<Token as Currency<_>>::deposit_into_existing(&4, 50).unwrap().offset(slashed.merge(burned)).is_ok());
+            drop(<Token as Currency<_>>::make_free_balance_be(&2, 10));
+
+            let ledger = ShadowLedger::replay();
+            let supply = ledger.balances.values().sum::<u64>() + ledger.reserved.values().sum::<u64>();
+            assert_eq!(supply, Token::total_supply());
+            assert_eq!(ledger.locks.get(&(1, listing)), Some(&70));
+            ledger.assert_matches_storage();
+        });
+    }
+
+    #[test]
+    fn should_approve_and_transfer_from() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 100));
+            assert_eq!(Token::allowance((1, 2)), 100);
+
+            // Spender can send to any account.
+            assert_ok!(Token::transfer_from(Origin::signed(2), 1, 3, 60));
+            assert_eq!(Token::balance_of(1), 940);
+            assert_eq!(Token::balance_of(3), 60);
+            assert_eq!(Token::balance_of(2), 0);
+            assert_eq!(Token::allowance((1, 2)), 40);
+
+            assert_noop!(Token::transfer_from(Origin::signed(2), 1, 2, 41), Error::AllowanceExceeded);
+            assert_ok!(Token::transfer_from(Origin::signed(2), 1, 2, 40));
+            assert_eq!(Token::allowance((1, 2)), 0);
+        });
+    }
+
+    #[test]
+    fn should_fail_transfer_from_without_allowance() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 100));
+
+            // The recipient of an allowance is not the spender.
+            assert_noop!(Token::transfer_from(Origin::signed(3), 1, 2, 10), Error::AllowanceNotFound);
+            // The spender cannot use the allowance for another owner.
+            assert_noop!(Token::transfer_from(Origin::signed(2), 3, 2, 10), Error::AllowanceNotFound);
+        });
+    }
+
+    #[test]
+    fn should_keep_allowance_on_failed_transfer_from() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::transfer(Origin::signed(1), 2, 50));
+            assert_ok!(Token::approve(Origin::signed(2), 3, 100));
+
+            assert_noop!(Token::transfer_from(Origin::signed(3), 2, 3, 60), Error::InsufficientBalance);
+            assert_eq!(Token::allowance((2, 3)), 100);
+        });
+    }
+
+    #[test]
+    fn should_replace_allowance_on_approve() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 100));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 30));
+            assert_eq!(Token::allowance((1, 2)), 30);
+            assert_ok!(Token::approve(Origin::signed(1), 2, 0));
+            assert_eq!(Token::allowance((1, 2)), 0);
+            assert_noop!(Token::approve(Origin::signed(4), 2, 10), Error::NotTokenHolder);
+        });
+    }
+
+    #[test]
+    fn should_increase_and_decrease_allowance() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::increase_allowance(Origin::signed(1), 2, 100));
+            assert_ok!(Token::increase_allowance(Origin::signed(1), 2, 50));
+            assert_eq!(Token::allowance((1, 2)), 150);
+
+            assert_ok!(Token::decrease_allowance(Origin::signed(1), 2, 120));
+            assert_eq!(Token::allowance((1, 2)), 30);
+            assert_noop!(
+                Token::decrease_allowance(Origin::signed(1), 2, 31),
+                Error::AllowanceUnderflow
+            );
+            assert_noop!(
+                Token::increase_allowance(Origin::signed(1), 2, u64::max_value()),
+                Error::Overflow
+            );
+        });
+    }
+
+    #[test]
+    fn should_set_allowance_only_from_expected_value() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 100));
+
+            // Spender front-runs the change and uses part of the old allowance.
+            assert_ok!(Token::transfer_from(Origin::signed(2), 1, 2, 80));
+            assert_noop!(
+                Token::set_allowance(Origin::signed(1), 2, 100, 50),
+                Error::UnexpectedAllowance
+            );
+
+            assert_ok!(Token::set_allowance(Origin::signed(1), 2, 20, 50));
+            assert_eq!(Token::allowance((1, 2)), 50);
+        });
+    }
+
+    #[test]
+    fn should_emit_approval_with_new_allowance() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::approve(Origin::signed(1), 2, 100));
+            assert_ok!(Token::transfer_from(Origin::signed(2), 1, 3, 60));
+
+            let events: Vec<TestEvent> = <system::Module<Test>>::events().into_iter().map(|r| r.event).collect();
+            assert_eq!(events, vec![
+                TestEvent::token(RawEvent::Initialized(1, 1000)),
+                TestEvent::token(RawEvent::Approval(1, 2, 100)),
+                TestEvent::token(RawEvent::Transfer(1, 3, 60)),
+                TestEvent::token(RawEvent::Approval(1, 2, 40)),
+            ]);
+        });
+    }
+
+    #[test]
+    fn should_fail_init_by_others() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_noop!(
+                Token::init(Origin::signed(2)),
+                Error::NotGenesisOwner
+            );
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_noop!(Token::init(Origin::signed(1)), Error::AlreadyInitialized);
+        });
+    }
+
+    #[test]
+    fn should_reserve_and_repatriate() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::transfer(Origin::signed(1), 2, 100));
+
+            // Full balance can be reserved.
+            assert!(<Token as ReservableCurrency<_>>::can_reserve(&2, 100));
+            assert!(!<Token as ReservableCurrency<_>>::can_reserve(&2, 101));
+            assert_ok!(<Token as ReservableCurrency<_>>::reserve(&2, 100));
+            assert_eq!(Token::balance_of(2), 0);
+            assert_eq!(<Token as ReservableCurrency<_>>::reserved_balance(&2), 100);
+            assert_eq!(<Token as Currency<_>>::total_balance(&2), 100);
+            assert_noop!(<Token as ReservableCurrency<_>>::reserve(&2, 1), "Not enough balance.");
+
+            // Only what is reserved can be unreserved or repatriated.
+            assert_eq!(<Token as ReservableCurrency<_>>::unreserve(&2, 30), 0);
+            assert_eq!(<Token as ReservableCurrency<_>>::repatriate_reserved(&2, &3, 80), Ok(10));
+            assert_eq!(Token::balance_of(2), 30);
+            assert_eq!(Token::balance_of(3), 70);
+            assert_eq!(Token::reserved_balance_of(2), 0);
+
+            ShadowLedger::replay().assert_matches_storage();
+        });
+    }
+
+    #[test]
+    fn should_lock_full_balance() {
+        with_externalities(&mut new_test_ext(), || {
+            let listing = H256::from_low_u64_be(1);
+
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+
+            // `can_lock` agrees with `lock`, around the exact balance too.
+            for &value in &[301, 300, 1, 0] {
+                assert_eq!(Token::can_lock(&2, value), Token::lock(2, value, listing).is_ok());
+            }
+            assert_eq!(Token::balance_of(2), 0);
+            assert_eq!(Token::locks(&2, &listing), 300);
+
+            assert!(!Token::can_lock(&4, 0));
+            assert_noop!(Token::lock(4, 0, listing), Error::NotTokenHolder);
+            assert_noop!(Token::lock(2, 1, listing), Error::InsufficientBalance);
+        });
+    }
+
+    #[test]
+    fn should_unlock_only_what_was_locked_for_the_hash() {
+        with_externalities(&mut new_test_ext(), || {
+            let (listing1, listing2) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_ok!(Token::transfer(Origin::signed(1), 2, 300));
+
+            assert_ok!(Token::lock(2, 100, listing1));
+            assert_ok!(Token::lock(2, 50, listing2));
+            assert_ok!(<Token as ReservableCurrency<_>>::reserve(&2, 20));
+            assert_eq!(Token::locks(&2, &listing1), 100);
+            assert_eq!(Token::locked_balance_of(2), 150);
+            assert_eq!(Token::reserved_balance_of(2), 170);
+            assert_eq!(Token::balance_of(2), 130);
+
+            // The lock of one hash cannot release the tokens of another.
+            assert_noop!(Token::unlock(2, 60, listing2), Error::InsufficientLock);
+            assert_noop!(Token::unlock(3, 1, listing1), Error::InsufficientLock);
+            // Locked tokens cannot be released through the `ReservableCurrency` interface either.
+            assert_eq!(<Token as ReservableCurrency<_>>::unreserve(&2, 100), 80);
+            assert_eq!(<Token as ReservableCurrency<_>>::repatriate_reserved(&2, &3, 10), Ok(10));
+            assert_eq!(Token::reserved_balance_of(2), 150);
+
+            assert_ok!(Token::unlock(2, 100, listing1));
+            assert_eq!(Token::locks(&2, &listing1), 0);
+            assert_eq!(Token::locks(&2, &listing2), 50);
+            assert_eq!(Token::locked_balance_of(2), 50);
+            assert_eq!(Token::balance_of(2), 250);
+
+            ShadowLedger::replay().assert_matches_storage();
+        });
+    }
+
+    #[test]
+    fn should_keep_total_supply_with_imbalances() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+
+            // Withdrawn tokens are destroyed when the imbalance is dropped.
+            let withdrawn = <Token as Currency<_>>::withdraw(
+                &1, 100, WithdrawReasons::all(), ExistenceRequirement::KeepAlive
+            ).unwrap();
+            assert_eq!(withdrawn.peek(), 100);
+            drop(withdrawn);
+            assert_eq!(Token::total_supply(), 900);
+            assert_eq!(
+                <system::Module<Test>>::events().pop().map(|record| record.event),
+                Some(TestEvent::token(RawEvent::Burned(1, 100)))
+            );
+
+            // Moving tokens with a matching imbalance keeps the total supply.
+            let (slashed, remaining) = <Token as Currency<_>>::slash(&1, 50);
+            assert_eq!(remaining, 0);
+            let deposited = <Token as Currency<_>>::deposit_creating(&2, 50);
+            assert!(deposited.offset(slashed).ok().map_or(false, |rest| rest.drop_zero().is_ok()));
+            assert_eq!(Token::total_supply(), 900);
+            assert_eq!(Token::balance_of(2), 50);
+            assert_eq!(Token::balance_of(1), 850);
+            ShadowLedger::replay().assert_matches_storage();
+        });
+    }
+
+    #[test]
+    fn should_mint_and_burn() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_noop!(Token::mint(system::RawOrigin::Root.into(), 2, 100), Error::NotInitialized);
+            assert_ok!(Token::init(Origin::signed(1)));
+
+            // Only root can mint.
+            assert!(Token::mint(Origin::signed(1), 2, 100).is_err());
+            assert_ok!(Token::mint(system::RawOrigin::Root.into(), 2, 100));
+            assert_eq!(Token::balance_of(2), 100);
+            assert_eq!(Token::total_supply(), 1100);
+
+            assert_noop!(Token::burn(Origin::signed(2), 101), Error::InsufficientBalance);
+            assert_ok!(Token::burn(Origin::signed(2), 40));
+            assert_eq!(Token::balance_of(2), 60);
+            assert_eq!(Token::total_supply(), 1060);
+
+            ShadowLedger::replay().assert_matches_storage();
+        });
+    }
+
+    #[test]
+    fn should_respect_max_supply() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::init(Origin::signed(1)));
+            assert_noop!(
+                Token::mint(system::RawOrigin::Root.into(), 2, 501),
+                Error::MaxSupplyExceeded
+            );
+            assert_ok!(Token::mint(system::RawOrigin::Root.into(), 2, 500));
+            assert_eq!(Token::total_supply(), 1500);
+
+            // Burned tokens can be minted again.
+            assert_ok!(Token::burn(Origin::signed(1), 100));
+            assert_ok!(Token::mint(system::RawOrigin::Root.into(), 3, 100));
+
+            // Issuance through the `Currency` interface is capped as well.
+            let issued = <Token as Currency<_>>::issue(100);
+            assert_eq!(issued.peek(), 0);
+            assert_eq!(Token::total_supply(), 1500);
+
+            // So are deposits.
+            assert_eq!(
+                <Token as Currency<_>>::deposit_into_existing(&2, 1).map(|imbalance| imbalance.peek()),
+                Err(<&'static str>::from(Error::MaxSupplyExceeded))
+            );
+            assert_eq!(<Token as Currency<_>>::deposit_creating(&4, 100).peek(), 0);
+            assert_eq!(Token::balance_of(2), 500);
+            assert_eq!(Token::balance_of(4), 0);
+            assert_eq!(Token::total_supply(), 1500);
+        });
+    }
+}
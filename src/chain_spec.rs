@@ -1,7 +1,7 @@
 use primitives::{Pair, Public};
 use substrate_tcr_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig,
-	SudoConfig, IndicesConfig, SystemConfig, TcrConfig, TokenConfig, WASM_BINARY, 
+	AccountId, AuraConfig, TokenBalance, BalancesConfig, GenesisConfig, GrandpaConfig,
+	SudoConfig, IndicesConfig, SystemConfig, TcrConfig, Tcr2Config, TokenConfig, Permill, WASM_BINARY, 
 };
 use aura_primitives::sr25519::{AuthorityId as AuraId};
 use grandpa_primitives::{AuthorityId as GrandpaId};
@@ -108,6 +108,14 @@ fn testnet_genesis(initial_authorities: Vec<(AuraId, GrandpaId)>,
 	root_key: AccountId, 
 	endowed_accounts: Vec<AccountId>,
 	_enable_println: bool) -> GenesisConfig {
+	// setting total supply of tokens to 21M because `Satoshi` said so
+	let token_supply: TokenBalance = 21000000;
+	// shared by the endowed accounts, the remainder of the division goes to the first one
+	assert!(!endowed_accounts.is_empty(), "Testnet genesis needs at least one endowed account to hold the token supply.");
+	let token_share = token_supply / endowed_accounts.len() as TokenBalance;
+	let mut token_balances: Vec<(AccountId, TokenBalance)> = endowed_accounts.iter().cloned().map(|k| (k, token_share)).collect();
+	token_balances[0].1 += token_supply - token_share * endowed_accounts.len() as TokenBalance;
+
 	GenesisConfig {
 		system: Some(SystemConfig {
 			code: WASM_BINARY.to_vec(),
@@ -130,8 +138,16 @@ fn testnet_genesis(initial_authorities: Vec<(AuraId, GrandpaId)>,
 			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect(),
 		}),
 		token: Some(TokenConfig {
-			// setting total supply of tokens to 21M because `Satoshi` said so
-			total_supply: 21000000,
+			name: b"Substrate TCR Token".to_vec(),
+			symbol: b"TCR".to_vec(),
+			// balances are in the smallest unit, like `satoshi`
+			decimals: 8,
+			total_supply: token_supply,
+			owner: get_from_seed::<AccountId>("Alice"),
+			// no cap, root can mint more tokens
+			max_supply: None,
+			// dev accounts are endowed at genesis, no need to call `init`
+			balances: token_balances,
 		}),
 		tcr: Some(TcrConfig {
 			owner: get_from_seed::<AccountId>("Alice"),
@@ -141,9 +157,28 @@ fn testnet_genesis(initial_authorities: Vec<(AuraId, GrandpaId)>,
 			apply_stage_len: 120000,
 			// voting time limit - for testing its set to 4 mins (240000 millis)
 			commit_stage_len: 240000,
+			// vote reveal time limit - for testing its set to 2 mins (120000 millis)
+			reveal_stage_len: 120000,
+			// half of the losing stake goes to the winner of a challenge
+			dispensation_pct: Permill::from_percent(50),
 			// initial poll/challenge set to 1
 			// to avoid 0 values
 			poll_nonce: 1,
-		})
+			// the registry starts empty, Alice is its owner and only admin
+			initial_listings: vec![],
+			initial_admins: vec![],
+		}),
+		// second TCR instance, with its own registries and parameters
+		tcr_Instance2: Some(Tcr2Config {
+			owner: get_from_seed::<AccountId>("Alice"),
+			min_deposit: 50,
+			apply_stage_len: 60000,
+			commit_stage_len: 120000,
+			reveal_stage_len: 60000,
+			dispensation_pct: Permill::from_percent(50),
+			poll_nonce: 1,
+			initial_listings: vec![],
+			initial_admins: vec![],
+		}),
 	}
 }
@@ -0,0 +1,194 @@
+//! JSON-RPC extensions of the TCR node.
+//!
+//! The `tcr_*` and `token_*` methods answer queries at the best block using the runtime APIs,
+//! so clients do not need to compute storage keys or decode storage themselves.
+//! The `tcr_*` methods take the index of the TCR instance first, `0` for `Tcr` and `1` for `Tcr2`.
+
+use std::sync::Arc;
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sr_primitives::{generic::BlockId, traits::ProvideRuntimeApi};
+use substrate_client::blockchain::HeaderBackend;
+use substrate_tcr_runtime::{
+	opaque::Block, runtime_api::{TcrApi as TcrRuntimeApi, TokenApi as TokenRuntimeApi},
+	AccountId, Challenge, Hash, Listing, Moment, Poll, RegistryId, TcrInstance, TokenBalance, Vote,
+};
+
+/// Error code for failed runtime API calls.
+const RUNTIME_ERROR: i64 = 1;
+
+type ListingOf = Listing<TokenBalance, AccountId, Moment>;
+type ChallengeOf = Challenge<Hash, TokenBalance, AccountId, Moment>;
+
+/// TCR RPC methods.
+#[rpc]
+pub trait TcrApi {
+	/// Get a listing by its hash.
+	#[rpc(name = "tcr_getListing")]
+	fn listing(&self, instance: TcrInstance, hash: Hash) -> Result<Option<ListingOf>>;
+
+	/// Get a page of the listings of a registry, with their hashes.
+	/// Pages are at most 100 listings long.
+	#[rpc(name = "tcr_listListings")]
+	fn listings(&self, instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Result<Vec<(Hash, ListingOf)>>;
+
+	/// Get the whitelisted listings on a page of the listings of a registry, with their hashes.
+	/// Pages are at most 100 listings long.
+	#[rpc(name = "tcr_listWhitelisted")]
+	fn whitelisted(&self, instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Result<Vec<(Hash, ListingOf)>>;
+
+	/// Get a challenge by its id.
+	#[rpc(name = "tcr_getChallenge")]
+	fn challenge(&self, instance: TcrInstance, id: u32) -> Result<Option<ChallengeOf>>;
+
+	/// Get the poll of a challenge.
+	#[rpc(name = "tcr_getPoll")]
+	fn poll(&self, instance: TcrInstance, id: u32) -> Result<Option<Poll<Hash, TokenBalance>>>;
+
+	/// Get the vote of an account on a challenge.
+	#[rpc(name = "tcr_getVote")]
+	fn vote(&self, instance: TcrInstance, challenge_id: u32, account: AccountId) -> Result<Option<Vote<Hash, TokenBalance>>>;
+
+	/// Rewards an account can claim on a page of the challenges, by challenge id.
+	/// Pages are at most 100 challenges long.
+	#[rpc(name = "tcr_pendingRewards")]
+	fn pending_rewards(&self, instance: TcrInstance, account: AccountId, page: u32, page_size: u32) -> Result<Vec<(u32, TokenBalance)>>;
+}
+
+/// Token RPC methods.
+#[rpc]
+pub trait TokenApi {
+	/// Get the token balance of an account.
+	#[rpc(name = "token_balanceOf")]
+	fn balance_of(&self, account: AccountId) -> Result<TokenBalance>;
+
+	/// Get the name of the token.
+	#[rpc(name = "token_name")]
+	fn name(&self) -> Result<String>;
+
+	/// Get the ticker symbol of the token.
+	#[rpc(name = "token_symbol")]
+	fn symbol(&self) -> Result<String>;
+
+	/// Get the number of decimals used to display token balances.
+	#[rpc(name = "token_decimals")]
+	fn decimals(&self) -> Result<u8>;
+}
+
+/// Implementation of the TCR RPC methods.
+pub struct Tcr<C> {
+	client: Arc<C>,
+}
+
+impl<C> Tcr<C> {
+	/// Create new `Tcr` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Tcr { client }
+	}
+}
+
+impl<C> TcrApi for Tcr<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: TcrRuntimeApi<Block, AccountId, TokenBalance, Hash, Moment>,
+{
+	fn listing(&self, instance: TcrInstance, hash: Hash) -> Result<Option<ListingOf>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().listing(&at, instance, hash).map_err(runtime_error)
+	}
+
+	fn listings(&self, instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Result<Vec<(Hash, ListingOf)>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().listings(&at, instance, registry_id, page, page_size).map_err(runtime_error)
+	}
+
+	fn whitelisted(&self, instance: TcrInstance, registry_id: RegistryId, page: u32, page_size: u32) -> Result<Vec<(Hash, ListingOf)>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().whitelisted(&at, instance, registry_id, page, page_size).map_err(runtime_error)
+	}
+
+	fn challenge(&self, instance: TcrInstance, id: u32) -> Result<Option<ChallengeOf>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().challenge(&at, instance, id).map_err(runtime_error)
+	}
+
+	fn poll(&self, instance: TcrInstance, id: u32) -> Result<Option<Poll<Hash, TokenBalance>>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().poll(&at, instance, id).map_err(runtime_error)
+	}
+
+	fn vote(&self, instance: TcrInstance, challenge_id: u32, account: AccountId) -> Result<Option<Vote<Hash, TokenBalance>>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().vote(&at, instance, challenge_id, account).map_err(runtime_error)
+	}
+
+	fn pending_rewards(&self, instance: TcrInstance, account: AccountId, page: u32, page_size: u32) -> Result<Vec<(u32, TokenBalance)>> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().pending_rewards(&at, instance, account, page, page_size).map_err(runtime_error)
+	}
+}
+
+/// Implementation of the token RPC methods.
+pub struct Token<C> {
+	client: Arc<C>,
+}
+
+impl<C> Token<C> {
+	/// Create new `Token` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Token { client }
+	}
+}
+
+impl<C> TokenApi for Token<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: TokenRuntimeApi<Block, AccountId, TokenBalance>,
+{
+	fn balance_of(&self, account: AccountId) -> Result<TokenBalance> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().balance_of(&at, account).map_err(runtime_error)
+	}
+
+	fn name(&self) -> Result<String> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().name(&at).map(utf8_lossy).map_err(runtime_error)
+	}
+
+	fn symbol(&self) -> Result<String> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().symbol(&at).map(utf8_lossy).map_err(runtime_error)
+	}
+
+	fn decimals(&self) -> Result<u8> {
+		let at = best_block(&*self.client);
+		self.client.runtime_api().decimals(&at).map_err(runtime_error)
+	}
+}
+
+/// Instantiate all RPC extensions of the node.
+pub fn create<C, M>(client: Arc<C>) -> jsonrpc_core::IoHandler<M> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: TcrRuntimeApi<Block, AccountId, TokenBalance, Hash, Moment>,
+	C::Api: TokenRuntimeApi<Block, AccountId, TokenBalance>,
+	M: jsonrpc_core::Metadata + Default,
+{
+	let mut io = jsonrpc_core::IoHandler::default();
+	io.extend_with(TcrApi::to_delegate(Tcr::new(client.clone())));
+	io.extend_with(TokenApi::to_delegate(Token::new(client)));
+	io
+}
+
+fn best_block<C: HeaderBackend<Block>>(client: &C) -> BlockId<Block> {
+	BlockId::hash(client.info().best_hash)
+}
+
+fn utf8_lossy(bytes: Vec<u8>) -> String {
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn runtime_error<E: std::fmt::Debug>(err: E) -> Error {
+	Error {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Runtime API call failed.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
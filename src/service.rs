@@ -66,6 +66,9 @@ macro_rules! new_full_start {
 				import_setup = Some((grandpa_block_import, grandpa_link));
 
 				Ok(import_queue)
+			})?
+			.with_rpc_extensions(|client, _pool| -> jsonrpc_core::IoHandler<substrate_service::RpcMetadata> {
+				crate::rpc::create(client)
 			})?;
 
 		(builder, import_setup, inherent_data_providers)